@@ -14,9 +14,53 @@
 
 use crate::v1::*;
 
+impl Value {
+    /// Returns an explicit null value.
+    pub fn null() -> Self {
+        Self {
+            value: Some(value::Value::NullValue(NullValue::NullValue as i32)),
+        }
+    }
+
+    /// Returns true if the value is an explicit null value.
+    pub fn is_null(&self) -> bool {
+        matches!(self.value, Some(value::Value::NullValue(_)))
+    }
+
+    /// Returns the type of the value.
+    pub fn value_type(&self) -> ValueType {
+        match &self.value {
+            None => ValueType::Unspecified,
+            Some(v) => v.value_type(),
+        }
+    }
+}
+
+impl value::Value {
+    /// Returns the type of the value.
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Self::I64Value(_) => ValueType::I64,
+            Self::F64Value(_) => ValueType::F64,
+            Self::BlobValue(_) => ValueType::Blob,
+            Self::TextValue(_) => ValueType::Text,
+            Self::BoolValue(_) => ValueType::Bool,
+            Self::NullValue(_) => ValueType::Null,
+            Self::TimestampValue(_) => ValueType::Timestamp,
+            Self::DurationValue(_) => ValueType::Duration,
+            Self::DecimalValue(_) => ValueType::Decimal,
+            Self::ListValue(_) => ValueType::List,
+            Self::MapValue(_) => ValueType::Map,
+            Self::SetValue(_) => ValueType::Set,
+            Self::SortedSetValue(_) => ValueType::SortedSet,
+            Self::RangeValue(_) => ValueType::Range,
+        }
+    }
+}
+
 impl From<()> for Value {
     fn from(_: ()) -> Self {
-        Self::default()
+        Self::null()
     }
 }
 
@@ -27,13 +71,13 @@ impl From<Value> for () {
 }
 
 impl TryFrom<Value> for value::Value {
-    type Error = Value;
+    type Error = ConversionError;
 
     fn try_from(v: Value) -> Result<Self, Self::Error> {
         if let Some(v) = v.value {
             Ok(v)
         } else {
-            Err(v)
+            Err(ConversionError::new("any", v))
         }
     }
 }
@@ -61,7 +105,7 @@ impl<T: Into<value::Value>> From<Option<T>> for Value {
 }
 
 macro_rules! impl_type {
-    ($rust_type:ty, $value_type:path) => {
+    ($rust_type:ty, $value_type:path, $type_name:literal) => {
         impl From<$rust_type> for value::Value {
             fn from(v: $rust_type) -> Self {
                 $value_type(v)
@@ -69,55 +113,54 @@ macro_rules! impl_type {
         }
 
         impl TryFrom<value::Value> for $rust_type {
-            type Error = value::Value;
+            type Error = ConversionError;
 
             fn try_from(v: value::Value) -> Result<Self, Self::Error> {
                 if let $value_type(v) = v {
                     Ok(v)
                 } else {
-                    Err(v)
+                    Err(ConversionError::new($type_name, v))
                 }
             }
         }
 
         impl TryFrom<Value> for $rust_type {
-            type Error = Value;
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
                 if let Some($value_type(v)) = v.value {
                     Ok(v)
                 } else {
-                    Err(v)
+                    Err(ConversionError::new($type_name, v))
                 }
             }
         }
 
         impl TryFrom<Value> for Option<$rust_type> {
-            type Error = Value;
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
-                if let Some(v) = v.value {
-                    if let $value_type(v) = v {
-                        Ok(Some(v))
-                    } else {
-                        Err(v.into())
-                    }
-                } else {
-                    Ok(None)
+                match v.value {
+                    None | Some(value::Value::NullValue(_)) => Ok(None),
+                    Some(v) => v.try_into().map(Some),
                 }
             }
         }
     };
 }
 
-impl_type!(i64, value::Value::I64Value);
-impl_type!(f64, value::Value::F64Value);
-impl_type!(Vec<u8>, value::Value::BlobValue);
-impl_type!(String, value::Value::TextValue);
-impl_type!(ListValue, value::Value::ListValue);
-impl_type!(MapValue, value::Value::MapValue);
-impl_type!(SetValue, value::Value::SetValue);
-impl_type!(RangeValue, value::Value::RangeValue);
+impl_type!(i64, value::Value::I64Value, "i64");
+impl_type!(f64, value::Value::F64Value, "f64");
+impl_type!(Vec<u8>, value::Value::BlobValue, "blob");
+impl_type!(String, value::Value::TextValue, "text");
+impl_type!(Timestamp, value::Value::TimestampValue, "timestamp");
+impl_type!(Duration, value::Value::DurationValue, "duration");
+impl_type!(Decimal, value::Value::DecimalValue, "decimal");
+impl_type!(ListValue, value::Value::ListValue, "list");
+impl_type!(MapValue, value::Value::MapValue, "map");
+impl_type!(SetValue, value::Value::SetValue, "set");
+impl_type!(SortedSetValue, value::Value::SortedSetValue, "sorted_set");
+impl_type!(RangeValue, value::Value::RangeValue, "range");
 
 impl From<&[u8]> for value::Value {
     fn from(v: &[u8]) -> Self {
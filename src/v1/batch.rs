@@ -0,0 +1,218 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::v1::*;
+
+/// A builder to construct a [`BatchRequest`].
+///
+/// Expressions are grouped per database and collection, so selecting the same
+/// database or collection twice appends to the existing request. Expressions
+/// are numbered in the order they are added, which is the order used by the
+/// [`BatchShape`] of the batch to decode the response.
+///
+/// ```
+/// use engula_apis::v1::Batch;
+///
+/// let batch = Batch::new()
+///     .db("app")
+///     .collection("users")
+///     .get("alice")
+///     .set("bob", 42);
+/// let shape = batch.shape();
+/// let req = batch.build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    request: BatchRequest,
+    database: Option<usize>,
+    collection: Option<usize>,
+    positions: Vec<(usize, usize, usize)>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the database for subsequent collections.
+    pub fn db(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let databases = &mut self.request.databases;
+        let index = match databases.iter().position(|db| db.name == name) {
+            Some(index) => index,
+            None => {
+                databases.push(DatabaseRequest {
+                    name,
+                    ..Default::default()
+                });
+                databases.len() - 1
+            }
+        };
+        self.database = Some(index);
+        self.collection = None;
+        self
+    }
+
+    /// Selects the database of the id for subsequent collections.
+    pub fn db_id(mut self, id: u64) -> Self {
+        let databases = &mut self.request.databases;
+        let index = match databases.iter().position(|db| db.id == id) {
+            Some(index) => index,
+            None => {
+                databases.push(DatabaseRequest {
+                    id,
+                    ..Default::default()
+                });
+                databases.len() - 1
+            }
+        };
+        self.database = Some(index);
+        self.collection = None;
+        self
+    }
+
+    /// Sets the read options of the selected database.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no database is selected.
+    pub fn read_options(mut self, options: ReadOptions) -> Self {
+        let database = self.database.expect("no database is selected");
+        self.request.databases[database].read_options = Some(options);
+        self
+    }
+
+    /// Selects the collection for subsequent expressions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no database is selected.
+    pub fn collection(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let database = self.database.expect("no database is selected");
+        let collections = &mut self.request.databases[database].requests;
+        let index = match collections.iter().position(|co| co.name == name) {
+            Some(index) => index,
+            None => {
+                collections.push(CollectionRequest {
+                    name,
+                    ..Default::default()
+                });
+                collections.len() - 1
+            }
+        };
+        self.collection = Some(index);
+        self
+    }
+
+    /// Selects the collection of the id for subsequent expressions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no database is selected.
+    pub fn collection_id(mut self, id: u64) -> Self {
+        let database = self.database.expect("no database is selected");
+        let collections = &mut self.request.databases[database].requests;
+        let index = match collections.iter().position(|co| co.id == id) {
+            Some(index) => index,
+            None => {
+                collections.push(CollectionRequest {
+                    id,
+                    ..Default::default()
+                });
+                collections.len() - 1
+            }
+        };
+        self.collection = Some(index);
+        self
+    }
+
+    /// Adds an expression to the selected collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no collection is selected.
+    pub fn expr(mut self, expr: ObjectExpr) -> Self {
+        let (database, collection) = match (self.database, self.collection) {
+            (Some(database), Some(collection)) => (database, collection),
+            _ => panic!("no collection is selected"),
+        };
+        let exprs = &mut self.request.databases[database].requests[collection].exprs;
+        self.positions.push((database, collection, exprs.len()));
+        exprs.push(expr);
+        self
+    }
+
+    pub fn select(self, key: impl Into<Vec<u8>>, select: SelectExpr) -> Self {
+        self.expr(ObjectExpr::batch([key]).with_select(select))
+    }
+
+    pub fn mutate(self, key: impl Into<Vec<u8>>, mutate: MutateExpr) -> Self {
+        self.expr(ObjectExpr::batch([key]).with_mutate(mutate))
+    }
+
+    pub fn get(self, key: impl Into<Vec<u8>>) -> Self {
+        self.select(key, SelectExpr::get())
+    }
+
+    /// Gets the value of the object along with its version.
+    pub fn get_versioned(self, key: impl Into<Vec<u8>>) -> Self {
+        self.select(key, SelectExpr::get().with_version())
+    }
+
+    pub fn len(self, key: impl Into<Vec<u8>>) -> Self {
+        self.select(key, SelectExpr::len())
+    }
+
+    pub fn set(self, key: impl Into<Vec<u8>>, value: impl Into<Value>) -> Self {
+        self.mutate(key, MutateExpr::set(value))
+    }
+
+    pub fn delete(self, key: impl Into<Vec<u8>>) -> Self {
+        self.mutate(key, MutateExpr::delete())
+    }
+
+    pub fn add(self, key: impl Into<Vec<u8>>, value: impl Into<Value>) -> Self {
+        self.mutate(key, MutateExpr::add(value))
+    }
+
+    /// Adds a universe request to the batch.
+    pub fn universe(mut self, request: universe_request::Request) -> Self {
+        self.request.universes.push(UniverseRequest {
+            request: Some(request),
+        });
+        self
+    }
+
+    /// Applies the database requests of the batch as a single transaction.
+    pub fn atomic(mut self) -> Self {
+        self.request.atomic = true;
+        self
+    }
+
+    /// Returns the shape of the batch to decode its response.
+    pub fn shape(&self) -> BatchShape {
+        BatchShape::with_positions(&self.request, self.positions.clone())
+    }
+
+    pub fn build(self) -> BatchRequest {
+        self.request
+    }
+}
+
+impl From<Batch> for BatchRequest {
+    fn from(b: Batch) -> Self {
+        b.build()
+    }
+}
@@ -16,46 +16,46 @@ use crate::v1::*;
 
 impl From<bool> for value::Value {
     fn from(v: bool) -> Self {
-        Self::I64Value(v as i64)
+        Self::BoolValue(v)
     }
 }
 
+/// Converts a value to bool.
+///
+/// With the `legacy-bool` feature, i64 values are also accepted and converted
+/// to `v != 0`, to decode data written when bools were encoded as i64.
 impl TryFrom<value::Value> for bool {
-    type Error = value::Value;
+    type Error = ConversionError;
 
     fn try_from(v: value::Value) -> Result<Self, Self::Error> {
-        if let value::Value::I64Value(v) = v {
-            Ok(v != 0)
-        } else {
-            Err(v)
+        match v {
+            value::Value::BoolValue(v) => Ok(v),
+            #[cfg(feature = "legacy-bool")]
+            value::Value::I64Value(v) => Ok(v != 0),
+            _ => Err(ConversionError::new("bool", v)),
         }
     }
 }
 
 impl TryFrom<Value> for bool {
-    type Error = Value;
+    type Error = ConversionError;
 
     fn try_from(v: Value) -> Result<Self, Self::Error> {
-        if let Some(value::Value::I64Value(v)) = v.value {
-            Ok(v != 0)
+        if let Some(v) = v.value {
+            v.try_into()
         } else {
-            Err(v)
+            Err(ConversionError::new("bool", v))
         }
     }
 }
 
 impl TryFrom<Value> for Option<bool> {
-    type Error = Value;
+    type Error = ConversionError;
 
     fn try_from(v: Value) -> Result<Self, Self::Error> {
-        if let Some(v) = v.value {
-            if let value::Value::I64Value(v) = v {
-                Ok(Some(v != 0))
-            } else {
-                Err(v.into())
-            }
-        } else {
-            Ok(None)
+        match v.value {
+            None | Some(value::Value::NullValue(_)) => Ok(None),
+            Some(v) => v.try_into().map(Some),
         }
     }
 }
@@ -0,0 +1,110 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+
+use tonic::{
+    body::BoxBody,
+    client::GrpcService,
+    codegen::{Body, StdError},
+    Code, Response, Status,
+};
+
+use crate::v1::{
+    engula_client::EngulaClient, BatchRequest, BatchResponse, ScanRequest, ScanStream,
+};
+
+impl<T> EngulaClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::ResponseBody: Body + Send + 'static,
+    T::Error: Into<StdError>,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Scans a collection and converts the values of the objects to `V`.
+    ///
+    /// ```no_run
+    /// use engula_apis::v1::{engula_client::EngulaClient, *};
+    /// use tonic::transport::Channel;
+    ///
+    /// # async fn example(mut client: EngulaClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let req = ScanRequest::new("app", "users")
+    ///     .with_range(RangeExpr::from_bounds("a".to_owned().."n".to_owned()));
+    /// // Yields `Result<(Vec<u8>, i64), ScanError>` for each user.
+    /// let users = client.scan_as::<i64>(req).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan_as<V>(&mut self, req: ScanRequest) -> Result<ScanStream<V>, Status> {
+        let stream = self.scan(req).await?.into_inner();
+        Ok(ScanStream::new(stream))
+    }
+
+    /// Runs a transaction built by `f` as an atomic batch.
+    ///
+    /// `f` is called with a clone of the client to read what the transaction
+    /// depends on, and returns the batch to commit, usually guarded by
+    /// mutation conditions on the values it read. If the transaction aborts
+    /// on a conflict with a concurrent transaction, `f` is called again, up to
+    /// `max_attempts` times in total. Other errors, including failed mutation
+    /// conditions, are returned right away.
+    ///
+    /// ```no_run
+    /// use engula_apis::v1::{engula_client::EngulaClient, *};
+    /// use tonic::{transport::Channel, Status};
+    ///
+    /// # async fn example(mut client: EngulaClient<Channel>) -> Result<(), Status> {
+    /// // Moves 10 from alice to bob, unless alice changes in the meantime.
+    /// client
+    ///     .transact(3, |mut client| async move {
+    ///         let batch = Batch::new().db("bank").collection("accounts").get("alice");
+    ///         let shape = batch.shape();
+    ///         let resp = client.batch(batch.build()).await?.into_inner();
+    ///         let balance: i64 = shape
+    ///             .decode(resp)
+    ///             .and_then(|mut result| result.take(0))
+    ///             .map_err(|err| Status::internal(err.to_string()))?;
+    ///         let batch = Batch::new()
+    ///             .db("bank")
+    ///             .collection("accounts")
+    ///             .mutate("alice", MutateExpr::set_if_equals(balance, balance - 10))
+    ///             .add("bob", 10);
+    ///         Ok(batch.build())
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn transact<F, Fut>(
+        &mut self,
+        max_attempts: usize,
+        mut f: F,
+    ) -> Result<BatchResponse, Status>
+    where
+        T: Clone,
+        F: FnMut(Self) -> Fut,
+        Fut: Future<Output = Result<BatchRequest, Status>>,
+    {
+        let mut attempts = 0;
+        loop {
+            let mut req = f(self.clone()).await?;
+            req.atomic = true;
+            attempts += 1;
+            match self.batch(req).await {
+                Err(status) if status.code() == Code::Aborted && attempts < max_attempts => {}
+                res => return res.map(Response::into_inner),
+            }
+        }
+    }
+}
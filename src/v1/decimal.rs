@@ -0,0 +1,73 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use crate::v1::*;
+
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(d: rust_decimal::Decimal) -> Self {
+        Self {
+            value: d.to_string(),
+        }
+    }
+}
+
+impl TryFrom<Decimal> for rust_decimal::Decimal {
+    type Error = ConversionError;
+
+    fn try_from(d: Decimal) -> Result<Self, Self::Error> {
+        Self::from_str(&d.value)
+            .or_else(|_| Self::from_scientific(&d.value))
+            .map_err(|_| ConversionError::new("decimal within the range of rust_decimal", d))
+    }
+}
+
+impl From<rust_decimal::Decimal> for value::Value {
+    fn from(v: rust_decimal::Decimal) -> Self {
+        Decimal::from(v).into()
+    }
+}
+
+impl TryFrom<value::Value> for rust_decimal::Decimal {
+    type Error = ConversionError;
+
+    fn try_from(v: value::Value) -> Result<Self, Self::Error> {
+        Decimal::try_from(v)?.try_into()
+    }
+}
+
+impl TryFrom<Value> for rust_decimal::Decimal {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        Decimal::try_from(v)?.try_into()
+    }
+}
+
+impl TryFrom<Value> for Option<rust_decimal::Decimal> {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        Option::<Decimal>::try_from(v)?
+            .map(TryInto::try_into)
+            .transpose()
+    }
+}
+
+impl ListElement for rust_decimal::Decimal {
+    fn try_from_value(v: Value) -> Result<Self, ConversionError> {
+        v.try_into()
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use tonic::Status;
+
+use crate::v1::*;
+
+impl ObjectResult {
+    /// Returns the values of the result, or the error if there is one.
+    pub fn into_result(self) -> Result<Vec<Value>, ObjectError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.values),
+        }
+    }
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.code())?;
+        if !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+        if !self.key.is_empty() {
+            write!(f, " (key: {:?})", String::from_utf8_lossy(&self.key))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ObjectError {}
+
+/// An error returned when a value can not be converted to a Rust type.
+///
+/// The error describes the innermost element that failed to convert, located
+/// by [`ConversionError::path`] within the original value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionError {
+    expected: &'static str,
+    actual: &'static str,
+    path: Vec<String>,
+    value: Box<Value>,
+}
+
+impl ConversionError {
+    /// Creates an error that the value is not of the expected type.
+    pub fn new(expected: &'static str, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        Self {
+            expected,
+            actual: type_name(&value),
+            path: Vec::new(),
+            value: Box::new(value),
+        }
+    }
+
+    /// Returns the name of the expected type.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Returns the name of the actual type.
+    pub fn actual(&self) -> &'static str {
+        self.actual
+    }
+
+    /// Returns the path from the original value to the element that failed to
+    /// convert. The path is empty if the original value itself failed.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Returns the original value.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the original value.
+    pub fn into_value(self) -> Value {
+        *self.value
+    }
+
+    /// Prepends a segment to the path.
+    pub(crate) fn at(mut self, segment: impl Into<String>) -> Self {
+        self.path.insert(0, segment.into());
+        self
+    }
+
+    /// Replaces the original value.
+    pub(crate) fn with_value(mut self, value: impl Into<Value>) -> Self {
+        *self.value = value.into();
+        self
+    }
+
+    /// Replaces the original value with the one built from the current value.
+    pub(crate) fn map_value(mut self, f: impl FnOnce(Value) -> Value) -> Self {
+        *self.value = f(*self.value);
+        self
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expect {}, got {}", self.expected, self.actual)?;
+        for (i, segment) in self.path.iter().enumerate() {
+            if i == 0 {
+                write!(f, " at {}", segment)?;
+            } else if segment.starts_with('[') {
+                write!(f, "{}", segment)?;
+            } else {
+                write!(f, ".{}", segment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+fn type_name(v: &Value) -> &'static str {
+    match &v.value {
+        None => "none",
+        Some(value::Value::I64Value(_)) => "i64",
+        Some(value::Value::F64Value(_)) => "f64",
+        Some(value::Value::BlobValue(_)) => "blob",
+        Some(value::Value::TextValue(_)) => "text",
+        Some(value::Value::BoolValue(_)) => "bool",
+        Some(value::Value::NullValue(_)) => "null",
+        Some(value::Value::TimestampValue(_)) => "timestamp",
+        Some(value::Value::DurationValue(_)) => "duration",
+        Some(value::Value::DecimalValue(_)) => "decimal",
+        Some(value::Value::ListValue(v)) => {
+            let arrays = [
+                (!v.i64_value.is_empty(), "list<i64>"),
+                (!v.f64_value.is_empty(), "list<f64>"),
+                (!v.blob_value.is_empty(), "list<blob>"),
+                (!v.text_value.is_empty(), "list<text>"),
+                (!v.bool_value.is_empty(), "list<bool>"),
+                (!v.null_value.is_empty(), "list<null>"),
+                (!v.timestamp_value.is_empty(), "list<timestamp>"),
+                (!v.duration_value.is_empty(), "list<duration>"),
+                (!v.decimal_value.is_empty(), "list<decimal>"),
+                (!v.values.is_empty(), "list<any>"),
+            ];
+            let mut names = arrays.iter().filter(|(set, _)| *set).map(|(_, name)| *name);
+            match (names.next(), names.next()) {
+                (None, _) => "list",
+                (Some(name), None) => name,
+                (Some(_), Some(_)) => "list<mixed>",
+            }
+        }
+        Some(value::Value::MapValue(_)) => "map",
+        Some(value::Value::SetValue(_)) => "set",
+        Some(value::Value::SortedSetValue(_)) => "sorted_set",
+        Some(value::Value::RangeValue(_)) => "range",
+    }
+}
+
+/// An error returned when decoding a [`BatchResponse`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResponseError {
+    /// The response does not match the shape of the request.
+    ShapeMismatch(String),
+    /// The expression does not exist or its result has been taken.
+    InvalidIndex(usize),
+    /// The expression failed with an error.
+    Object(ObjectError),
+    /// The value can not be converted to the requested type.
+    Conversion(ConversionError),
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ShapeMismatch(msg) => write!(f, "response shape mismatch: {}", msg),
+            Self::InvalidIndex(n) => write!(f, "invalid expression index {}", n),
+            Self::Object(err) => write!(f, "object error: {}", err),
+            Self::Conversion(err) => write!(f, "conversion error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Object(err) => Some(err),
+            Self::Conversion(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An error returned by a [`ScanStream`].
+#[derive(Debug)]
+pub enum ScanError {
+    /// The scan failed with a status.
+    Status(Status),
+    /// The value can not be converted to the requested type.
+    Conversion(ConversionError),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "scan failed: {}", status),
+            Self::Conversion(err) => write!(f, "conversion error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Status(status) => Some(status),
+            Self::Conversion(err) => Some(err),
+        }
+    }
+}
@@ -0,0 +1,1625 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evaluation of expressions on object values.
+//!
+//! This module defines the semantics of [`SelectExpr`], [`MutateExpr`] and
+//! [`FilterExpr`] on a single object, so that servers and client-side caches
+//! can share one implementation. An object is an `Option<Value>`, which is
+//! `None` if the object does not exist. Unset values are treated as missing.
+//!
+//! # Index
+//!
+//! If an expression has an index, the function applies to an element of the
+//! object instead of the object itself:
+//!
+//! - For a list, the index is an i64 position. Negative positions count from
+//!   the end, so -1 is the last element.
+//! - For a map, the index is the key of the element.
+//!
+//! Indexing any other type is a `TYPE_MISMATCH`. Selecting an element that
+//! does not exist results in an unset value. A mutation that leaves the
+//! element unset removes it from the container. A mutation on a missing list
+//! position is `OUT_OF_RANGE`, and a mutation on an element of a missing
+//! object creates a map.
+//!
+//! `TRIM`, `SLICE`, `ZRANGE` and `ZRANGE_BY_SCORE` are the exceptions: their
+//! index is a [`RangeValue`] of list positions, ranks or scores.
+//!
+//! # Select
+//!
+//! | Function | Args | Result |
+//! | -------- | ---- | ------ |
+//! | `GET`    | none | The value, or unset if it does not exist. |
+//! | `VERSION`, `TTL` | none | Not functions of values. Servers resolve them themselves. |
+//! | `EXISTS` | none | Whether the value exists. |
+//! | `TYPE`   | none | The [`ValueType`] of the value as an i64, or `VALUE_TYPE_UNSPECIFIED` if it does not exist. |
+//! | `LEN`    | none | The number of elements of a container, or 0 if it does not exist. |
+//! | `CONTAINS` | value | Whether a list has an element, a map has a key, or a set or sorted set has a member equal to the argument. |
+//! | `KEYS`   | none | The keys of a map or the members of a set or sorted set as a list. |
+//! | `VALUES` | none | The values of a map or the scores of a sorted set as a list. |
+//! | `ITEMS`  | none | The entries of a map or sorted set as a list of `[key, value]` or `[member, score]` lists. |
+//! | `SLICE`  | none | The elements of a list in the index range as a list. |
+//! | `INDEX_OF` | value | The position of the first list element equal to the argument, or -1. |
+//! | `SINTER`, `SUNION`, `SDIFF` | sets | The intersection, union or difference of a set with the arguments as a set. |
+//! | `ZSCORE`, `ZRANK` | member | The score or rank of the member of a sorted set, or unset if it does not exist. |
+//! | `ZRANGE`, `ZRANGE_BY_SCORE` | none | The members of a sorted set in the index range as a sorted set. |
+//!
+//! `KEYS`, `VALUES`, `ITEMS`, `SLICE`, `ZRANGE` and `ZRANGE_BY_SCORE` result in
+//! an unset value if the value does not exist, and set functions treat it as
+//! an empty set.
+//!
+//! The aggregate functions `COUNT`, `SUM`, `MIN`, `MAX` and `AVG` aggregate
+//! the elements of a list or set, the values of a map or the scores of a
+//! sorted set, as described in the proto definitions. [`Aggregator`]
+//! aggregates values across objects, including the sets of `SINTER`, `SUNION`
+//! and `SDIFF`.
+//!
+//! # Mutate
+//!
+//! | Function | Args | Applies to | Effect | Result |
+//! | -------- | ---- | ---------- | ------ | ------ |
+//! | `SET`    | value | any | Replaces the value. | unset |
+//! | `DELETE` | none | any | Removes the value. | unset |
+//! | `ADD`    | number | i64, f64 | Adds the argument, starting from 0 if missing. | the sum |
+//! | `TRIM`   | none | list | Keeps the elements in the index range. | unset |
+//! | `LPOP`, `RPOP` | optional count | list | Removes elements from the front or back. | the element, or a list of elements in popping order if a count is given |
+//! | `LPUSH`, `RPUSH` | elements | list | Inserts the elements at the front or back, in argument order, creating the list if missing. | the new length |
+//! | `CLEAR`  | none | containers | Removes all elements, keeping the type. | unset |
+//! | `EXTEND` | container | containers | Appends a list, merges a map or sorted set, or adds the members of a set or list to a set. Creates the object if missing. | the new length |
+//! | `REMOVE` | values | containers | Removes list elements, map keys or members equal to any argument. | the number of removed elements |
+//! | `SINTER_STORE`, `SUNION_STORE`, `SDIFF_STORE` | sets | set | Replaces the set with the result of `SINTER`, `SUNION` or `SDIFF`. | the new length |
+//! | `SPOP`   | optional count | set | Removes random members. | the member, or a set of members if a count is given |
+//! | `ZADD`   | sorted set | sorted set | Adds members, replacing the scores of existing ones. | the number of new members |
+//! | `ZINCRBY` | member, number | sorted set | Adds to the score of the member, starting from 0 if missing. | the new score |
+//! | `EXPIRE`, `PERSIST` | | | Not functions of values. Servers apply them themselves. | |
+//!
+//! Integer addition that overflows is `OUT_OF_RANGE`, and adding an i64 to an
+//! f64 results in an f64. Applying a function to a type not listed is a
+//! `TYPE_MISMATCH`, and wrong arguments are an `INVALID_ARGUMENT`. Functions
+//! other than `SET`, `ADD`, `LPUSH`, `RPUSH`, `EXTEND`, the set store
+//! functions, `ZADD` and `ZINCRBY` do nothing to a missing object. On error,
+//! the object is left unchanged.
+//!
+//! # Filter
+//!
+//! Comparison and membership functions evaluate the select function of the
+//! filter on the object, and compare the result with the arguments following
+//! [`compare`]. Values that are not comparable never satisfy `LT`, `LE`, `GT`
+//! or `GE`, and are never equal.
+//!
+//! ```
+//! use engula_apis::v1::{eval, MutateExpr, SelectExpr, Value};
+//!
+//! let mut object = None;
+//! eval::apply_mutate(&mut object, &MutateExpr::add(1)).unwrap();
+//! eval::apply_mutate(&mut object, &MutateExpr::add(0.5)).unwrap();
+//! let value = eval::apply_select(&object, &SelectExpr::get()).unwrap();
+//! assert_eq!(value, Value::from(1.5));
+//! ```
+
+use std::{
+    cmp::Ordering,
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hasher},
+    ops::{Bound, RangeBounds},
+};
+
+use crate::v1::{value::Value as V, *};
+
+/// The result of an evaluation, which fails with an [`ObjectError`] without
+/// a key or index.
+pub type Result<T> = std::result::Result<T, ObjectError>;
+
+pub(crate) fn error(code: ObjectErrorCode, message: impl Into<String>) -> ObjectError {
+    ObjectError {
+        code: code as i32,
+        message: message.into(),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn invalid_argument(message: impl Into<String>) -> ObjectError {
+    error(ObjectErrorCode::InvalidArgument, message)
+}
+
+fn type_mismatch(func: impl Debug, value: &Value) -> ObjectError {
+    error(
+        ObjectErrorCode::TypeMismatch,
+        format!("{:?} does not apply to {:?}", func, value.value_type()),
+    )
+}
+
+fn one_arg(func: impl Debug, args: &[Value]) -> Result<&Value> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(invalid_argument(format!("{:?} expects one argument", func))),
+    }
+}
+
+fn no_args(func: impl Debug, args: &[Value]) -> Result<()> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_argument(format!("{:?} expects no arguments", func)))
+    }
+}
+
+/// Returns None for an unset value.
+fn present(value: Option<Value>) -> Option<Value> {
+    value.filter(|v| v.value.is_some())
+}
+
+fn list_values(list: ListValue) -> Result<Vec<Value>> {
+    list.into_values()
+        .map_err(|err| invalid_argument(err.to_string()))
+}
+
+/// A container value decomposed into its elements.
+enum Container {
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    SortedSet(Vec<(Value, f64)>),
+}
+
+impl Container {
+    /// Decomposes a value, or returns None if it is not a container.
+    fn from_value(value: &Value) -> Result<Option<Self>> {
+        let container = match &value.value {
+            Some(V::ListValue(list)) => Self::List(list_values(list.clone())?),
+            Some(V::MapValue(map)) => {
+                let (keys, values) = <(ListValue, ListValue)>::try_from(map.clone())
+                    .map_err(|err| invalid_argument(err.to_string()))?;
+                let keys = list_values(keys)?;
+                let values = list_values(values)?;
+                if keys.len() != values.len() {
+                    return Err(invalid_argument(
+                        "map with different numbers of keys and values",
+                    ));
+                }
+                Self::Map(keys.into_iter().zip(values).collect())
+            }
+            Some(V::SetValue(set)) => Self::Set(list_values(set.keys.clone().unwrap_or_default())?),
+            Some(V::SortedSetValue(set)) => {
+                let members = list_values(set.members.clone().unwrap_or_default())?;
+                if members.len() != set.scores.len() {
+                    return Err(invalid_argument(
+                        "sorted set with different numbers of members and scores",
+                    ));
+                }
+                if set.scores.iter().any(|s| s.is_nan()) {
+                    return Err(invalid_argument("sorted set with NaN scores"));
+                }
+                let mut members: Vec<_> = members.into_iter().zip(set.scores.clone()).collect();
+                sort_scored(&mut members);
+                Self::SortedSet(members)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(container))
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Self::List(values) => values.into(),
+            Self::Map(entries) => {
+                let (keys, values): (Vec<Value>, Vec<Value>) = entries.into_iter().unzip();
+                MapValue::from((keys, values)).into()
+            }
+            Self::Set(members) => SetValue::from(members).into(),
+            Self::SortedSet(mut members) => {
+                sort_scored(&mut members);
+                let (members, scores) = members.into_iter().unzip();
+                SortedSetValue {
+                    members: Some(ListValue::from_values(members)),
+                    scores,
+                }
+                .into()
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::List(values) | Self::Set(values) => values.len(),
+            Self::Map(entries) => entries.len(),
+            Self::SortedSet(members) => members.len(),
+        }
+    }
+
+    fn clear(self) -> Self {
+        match self {
+            Self::List(_) => Self::List(Vec::new()),
+            Self::Map(_) => Self::Map(Vec::new()),
+            Self::Set(_) => Self::Set(Vec::new()),
+            Self::SortedSet(_) => Self::SortedSet(Vec::new()),
+        }
+    }
+
+    /// Appends lists, merges maps and unions sets. A set can also be extended
+    /// with the elements of a list, and sorted sets take the scores of the
+    /// other sorted set.
+    fn extend(self, other: Self) -> Option<Self> {
+        let container = match (self, other) {
+            (Self::List(mut values), Self::List(other)) => {
+                values.extend(other);
+                Self::List(values)
+            }
+            (Self::Map(mut entries), Self::Map(other)) => {
+                for (key, value) in other {
+                    match entries.iter_mut().find(|(k, _)| equals(k, &key)) {
+                        Some(entry) => entry.1 = value,
+                        None => entries.push((key, value)),
+                    }
+                }
+                Self::Map(entries)
+            }
+            (Self::Set(mut members), Self::Set(other) | Self::List(other)) => {
+                for member in other {
+                    if !members.iter().any(|m| equals(m, &member)) {
+                        members.push(member);
+                    }
+                }
+                Self::Set(members)
+            }
+            (Self::SortedSet(mut members), Self::SortedSet(other)) => {
+                upsert_scored(&mut members, other);
+                Self::SortedSet(members)
+            }
+            _ => return None,
+        };
+        Some(container)
+    }
+
+    /// Removes list elements, map keys or set members that equal any of the
+    /// given values, and returns the number of removed elements.
+    fn remove(&mut self, args: &[Value]) -> usize {
+        let matches = |v: &Value| args.iter().any(|arg| equals(v, arg));
+        let len = self.len();
+        match self {
+            Self::List(values) | Self::Set(values) => values.retain(|v| !matches(v)),
+            Self::Map(entries) => entries.retain(|(k, _)| !matches(k)),
+            Self::SortedSet(members) => members.retain(|(m, _)| !matches(m)),
+        }
+        len - self.len()
+    }
+}
+
+/// Sorts the members of a sorted set by score, then by member.
+fn sort_scored(members: &mut [(Value, f64)]) {
+    members.sort_by(|(a, a_score), (b, b_score)| {
+        a_score
+            .partial_cmp(b_score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| compare(a, b).unwrap_or(Ordering::Equal))
+    });
+}
+
+/// Adds members to a sorted set, replacing the scores of existing members,
+/// and returns the number of new members.
+fn upsert_scored(members: &mut Vec<(Value, f64)>, other: Vec<(Value, f64)>) -> usize {
+    let mut added = 0;
+    for (member, score) in other {
+        match members.iter_mut().find(|(m, _)| equals(m, &member)) {
+            Some(entry) => entry.1 = score,
+            None => {
+                members.push((member, score));
+                added += 1;
+            }
+        }
+    }
+    added
+}
+
+/// Combines set members with the members of another set following SINTER,
+/// SUNION or SDIFF.
+fn combine(func: SelectFunction, mut members: Vec<Value>, other: &[Value]) -> Vec<Value> {
+    let contains = |m: &Value| other.iter().any(|o| equals(m, o));
+    match func {
+        SelectFunction::Sinter => members.retain(contains),
+        SelectFunction::Sdiff => members.retain(|m| !contains(m)),
+        _ => {
+            for o in other {
+                if !members.iter().any(|m| equals(m, o)) {
+                    members.push(o.clone());
+                }
+            }
+        }
+    }
+    members
+}
+
+/// Returns a random position of a list of the given length, which must not be
+/// zero.
+fn random_position(len: usize) -> usize {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(len);
+    (hasher.finish() % len as u64) as usize
+}
+
+/// Resolves a list index, counting from the end if it is negative.
+///
+/// Returns None if the index is out of range.
+fn position(len: usize, index: &Value) -> Result<Option<usize>> {
+    let index = match index.value {
+        Some(V::I64Value(index)) => index,
+        _ => return Err(invalid_argument("expect an i64 list index")),
+    };
+    let len = len as i64;
+    let pos = if index < 0 { index + len } else { index };
+    Ok((0..len).contains(&pos).then(|| pos as usize))
+}
+
+/// Resolves a range of list positions, counting from the end for negative
+/// bounds. The resolved range is clamped to the list.
+fn positions(len: usize, range: &Value) -> Result<std::ops::Range<usize>> {
+    let range = match &range.value {
+        Some(V::RangeValue(range)) => range,
+        _ => return Err(invalid_argument("expect a range of list positions")),
+    };
+    let len = len as i64;
+    let bound = |bound: &Option<RangeBound>| -> Result<Option<(i64, bool)>> {
+        match bound
+            .as_ref()
+            .and_then(|b| b.value.as_ref().map(|v| (v, b.included)))
+        {
+            None => Ok(None),
+            Some((range_bound::Value::I64Value(i), included)) => {
+                Ok(Some((if *i < 0 { i + len } else { *i }, included)))
+            }
+            Some(_) => Err(invalid_argument("expect i64 bounds of list positions")),
+        }
+    };
+    let start = match bound(&range.start)? {
+        None => 0,
+        Some((i, included)) => i.saturating_add(!included as i64),
+    };
+    let end = match bound(&range.end)? {
+        None => len,
+        Some((i, included)) => i.saturating_add(included as i64),
+    };
+    let start = start.clamp(0, len);
+    let end = end.clamp(start, len);
+    Ok(start as usize..end as usize)
+}
+
+/// Resolves a range of scores with i64 or f64 bounds.
+fn score_bounds(range: &Value) -> Result<(Bound<f64>, Bound<f64>)> {
+    let range = match &range.value {
+        Some(V::RangeValue(range)) => range,
+        _ => return Err(invalid_argument("expect a range of scores")),
+    };
+    let bound = |bound: &Option<RangeBound>| -> Result<Bound<f64>> {
+        let (score, included) = match bound
+            .as_ref()
+            .and_then(|b| b.value.as_ref().map(|v| (v, b.included)))
+        {
+            None => return Ok(Bound::Unbounded),
+            Some((range_bound::Value::I64Value(i), included)) => (*i as f64, included),
+            Some((range_bound::Value::F64Value(f), included)) => (*f, included),
+            Some(_) => return Err(invalid_argument("expect i64 or f64 bounds of scores")),
+        };
+        Ok(if included {
+            Bound::Included(score)
+        } else {
+            Bound::Excluded(score)
+        })
+    };
+    Ok((bound(&range.start)?, bound(&range.end)?))
+}
+
+/// Compares two values.
+///
+/// Integers and floats compare numerically with each other. Blobs and texts
+/// compare bytewise, and lists compare lexicographically. Maps, sets, sorted
+/// sets and ranges are only equal or not. Values of different types are not
+/// comparable.
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (&a.value, &b.value) {
+        (None, None) => Some(Ordering::Equal),
+        (Some(V::I64Value(a)), Some(V::I64Value(b))) => Some(a.cmp(b)),
+        (Some(V::I64Value(a)), Some(V::F64Value(b))) => (*a as f64).partial_cmp(b),
+        (Some(V::F64Value(a)), Some(V::I64Value(b))) => a.partial_cmp(&(*b as f64)),
+        (Some(V::F64Value(a)), Some(V::F64Value(b))) => a.partial_cmp(b),
+        (Some(V::BlobValue(a)), Some(V::BlobValue(b))) => Some(a.cmp(b)),
+        (Some(V::TextValue(a)), Some(V::TextValue(b))) => Some(a.cmp(b)),
+        (Some(V::BoolValue(a)), Some(V::BoolValue(b))) => Some(a.cmp(b)),
+        (Some(V::NullValue(_)), Some(V::NullValue(_))) => Some(Ordering::Equal),
+        (Some(V::TimestampValue(a)), Some(V::TimestampValue(b))) => {
+            Some((a.seconds, a.nanos).cmp(&(b.seconds, b.nanos)))
+        }
+        (Some(V::DurationValue(a)), Some(V::DurationValue(b))) => {
+            Some((a.seconds, a.nanos).cmp(&(b.seconds, b.nanos)))
+        }
+        (Some(V::DecimalValue(a)), Some(V::DecimalValue(b))) => compare_decimal(&a.value, &b.value),
+        (Some(V::ListValue(a)), Some(V::ListValue(b))) => {
+            let a = list_values(a.clone()).ok()?;
+            let b = list_values(b.clone()).ok()?;
+            for (a, b) in a.iter().zip(&b) {
+                match compare(a, b)? {
+                    Ordering::Equal => continue,
+                    ord => return Some(ord),
+                }
+            }
+            Some(a.len().cmp(&b.len()))
+        }
+        (Some(V::MapValue(_)), Some(V::MapValue(_)))
+        | (Some(V::SetValue(_)), Some(V::SetValue(_)))
+        | (Some(V::SortedSetValue(_)), Some(V::SortedSetValue(_))) => {
+            let a = Container::from_value(a).ok()??;
+            let b = Container::from_value(b).ok()??;
+            let equal = a.len() == b.len()
+                && match (&a, &b) {
+                    (Container::Map(a), Container::Map(b)) => a
+                        .iter()
+                        .all(|(k, v)| b.iter().any(|(bk, bv)| equals(k, bk) && equals(v, bv))),
+                    (Container::Set(a), Container::Set(b)) => {
+                        a.iter().all(|m| b.iter().any(|bm| equals(m, bm)))
+                    }
+                    (Container::SortedSet(a), Container::SortedSet(b)) => a
+                        .iter()
+                        .all(|(m, s)| b.iter().any(|(bm, bs)| equals(m, bm) && s == bs)),
+                    _ => false,
+                };
+            equal.then(|| Ordering::Equal)
+        }
+        (Some(V::RangeValue(a)), Some(V::RangeValue(b))) => (a == b).then(|| Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// Returns true if two values compare equal.
+pub fn equals(a: &Value, b: &Value) -> bool {
+    compare(a, b) == Some(Ordering::Equal)
+}
+
+/// Splits a decimal string into its sign, integral and fractional digits,
+/// without insignificant zeros.
+fn parse_decimal(s: &str) -> Option<(bool, &str, &str)> {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+        return None;
+    }
+    let int = int.trim_start_matches('0');
+    let frac = frac.trim_end_matches('0');
+    let negative = negative && !(int.is_empty() && frac.is_empty());
+    Some((negative, int, frac))
+}
+
+fn compare_decimal(a: &str, b: &str) -> Option<Ordering> {
+    let (a_negative, a_int, a_frac) = parse_decimal(a)?;
+    let (b_negative, b_int, b_frac) = parse_decimal(b)?;
+    let abs = a_int
+        .len()
+        .cmp(&b_int.len())
+        .then_with(|| a_int.cmp(b_int))
+        .then_with(|| a_frac.cmp(b_frac));
+    Some(match (a_negative, b_negative) {
+        (false, false) => abs,
+        (true, true) => abs.reverse(),
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+    })
+}
+
+/// Returns the list element or map value at the index of an object.
+fn get_element(object: &Option<Value>, index: &Value) -> Result<Option<Value>> {
+    let value = match object {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match Container::from_value(value)? {
+        Some(Container::List(mut values)) => {
+            Ok(position(values.len(), index)?.map(|pos| values.swap_remove(pos)))
+        }
+        Some(Container::Map(entries)) => Ok(entries
+            .into_iter()
+            .find(|(k, _)| equals(k, index))
+            .map(|(_, v)| v)),
+        _ => Err(type_mismatch("index", value)),
+    }
+}
+
+/// Applies a function to the list element or map value at the index of an
+/// object, and puts the element back afterwards.
+///
+/// An element that becomes unset is removed. A missing object is created as a
+/// map if the element is set.
+fn with_element<T>(
+    object: &mut Option<Value>,
+    index: &Value,
+    f: impl FnOnce(&mut Option<Value>) -> Result<T>,
+) -> Result<T> {
+    let container = match object {
+        None => Container::Map(Vec::new()),
+        Some(value) => match Container::from_value(value)? {
+            Some(Container::Set(_) | Container::SortedSet(_)) | None => {
+                return Err(type_mismatch("index", value))
+            }
+            Some(container) => container,
+        },
+    };
+    let output = match container {
+        Container::List(mut values) => {
+            let pos = position(values.len(), index)?
+                .ok_or_else(|| error(ObjectErrorCode::OutOfRange, "list index out of range"))?;
+            let mut element = Some(std::mem::take(&mut values[pos]));
+            let output = f(&mut element)?;
+            match present(element) {
+                Some(element) => values[pos] = element,
+                None => {
+                    values.remove(pos);
+                }
+            }
+            *object = Some(Container::List(values).into_value());
+            output
+        }
+        Container::Map(mut entries) => {
+            let pos = entries.iter().position(|(k, _)| equals(k, index));
+            let mut element = pos.map(|pos| entries[pos].1.clone());
+            let output = f(&mut element)?;
+            match (pos, present(element)) {
+                (Some(pos), Some(element)) => entries[pos].1 = element,
+                (Some(pos), None) => {
+                    entries.remove(pos);
+                }
+                (None, Some(element)) => entries.push((index.clone(), element)),
+                (None, None) => {}
+            }
+            if object.is_some() || !entries.is_empty() {
+                *object = Some(Container::Map(entries).into_value());
+            }
+            output
+        }
+        Container::Set(_) | Container::SortedSet(_) => unreachable!(),
+    };
+    Ok(output)
+}
+
+/// Evaluates a select expression on an object, which is None if the object
+/// does not exist.
+///
+/// VERSION and TTL are not supported, since they are kept by servers.
+pub fn apply_select(object: &Option<Value>, expr: &SelectExpr) -> Result<Value> {
+    let func = SelectFunction::from_i32(expr.func)
+        .ok_or_else(|| invalid_argument(format!("unknown select function {}", expr.func)))?;
+    if matches!(
+        func,
+        SelectFunction::Slice | SelectFunction::Zrange | SelectFunction::ZrangeByScore
+    ) {
+        let range = expr
+            .index
+            .as_ref()
+            .ok_or_else(|| invalid_argument(format!("{:?} expects a range index", func)))?;
+        let value = match object {
+            Some(value) => value,
+            None => return Ok(Value::default()),
+        };
+        let value = match func {
+            SelectFunction::Slice => {
+                let values = list_of(func, value)?;
+                let range = positions(values.len(), range)?;
+                values[range].to_vec().into()
+            }
+            SelectFunction::Zrange => {
+                let members = sorted_set_of(func, value)?;
+                let range = positions(members.len(), range)?;
+                Container::SortedSet(members[range].to_vec()).into_value()
+            }
+            _ => {
+                let range = score_bounds(range)?;
+                let mut members = sorted_set_of(func, value)?;
+                members.retain(|(_, score)| range.contains(score));
+                Container::SortedSet(members).into_value()
+            }
+        };
+        return Ok(value);
+    }
+
+    let element;
+    let target = match &expr.index {
+        Some(index) => {
+            element = get_element(object, index)?;
+            &element
+        }
+        None => object,
+    };
+    let args = expr.args.as_slice();
+    let value = match func {
+        SelectFunction::Get => target.clone().unwrap_or_default(),
+        SelectFunction::Version | SelectFunction::Ttl => {
+            return Err(invalid_argument(format!(
+                "{:?} is not a function of values",
+                func
+            )))
+        }
+        SelectFunction::Exists => target.is_some().into(),
+        SelectFunction::Type => {
+            let value_type = target
+                .as_ref()
+                .map_or(ValueType::Unspecified, Value::value_type);
+            (value_type as i64).into()
+        }
+        SelectFunction::Len => match target {
+            Some(value) => (container_of(func, value)?.len() as i64).into(),
+            None => 0.into(),
+        },
+        SelectFunction::Contains => {
+            let arg = one_arg(func, args)?;
+            let contains = match target {
+                Some(value) => match container_of(func, value)? {
+                    Container::List(values) | Container::Set(values) => {
+                        values.iter().any(|v| equals(v, arg))
+                    }
+                    Container::Map(entries) => entries.iter().any(|(k, _)| equals(k, arg)),
+                    Container::SortedSet(members) => members.iter().any(|(m, _)| equals(m, arg)),
+                },
+                None => false,
+            };
+            contains.into()
+        }
+        SelectFunction::Keys | SelectFunction::Values | SelectFunction::Items => {
+            let value = match target {
+                Some(value) => value,
+                None => return Ok(Value::default()),
+            };
+            let elements: Vec<Value> = match (func, container_of(func, value)?) {
+                (SelectFunction::Keys, Container::Set(members)) => members,
+                (SelectFunction::Keys, Container::Map(entries)) => {
+                    entries.into_iter().map(|(k, _)| k).collect()
+                }
+                (SelectFunction::Values, Container::Map(entries)) => {
+                    entries.into_iter().map(|(_, v)| v).collect()
+                }
+                (SelectFunction::Items, Container::Map(entries)) => entries
+                    .into_iter()
+                    .map(|(k, v)| vec![k, v].into())
+                    .collect(),
+                (SelectFunction::Keys, Container::SortedSet(members)) => {
+                    members.into_iter().map(|(m, _)| m).collect()
+                }
+                (SelectFunction::Values, Container::SortedSet(members)) => {
+                    members.into_iter().map(|(_, s)| s.into()).collect()
+                }
+                (SelectFunction::Items, Container::SortedSet(members)) => members
+                    .into_iter()
+                    .map(|(m, s)| vec![m, s.into()].into())
+                    .collect(),
+                _ => return Err(type_mismatch(func, value)),
+            };
+            elements.into()
+        }
+        SelectFunction::IndexOf => {
+            let arg = one_arg(func, args)?;
+            let values = match target {
+                Some(value) => list_of(func, value)?,
+                None => Vec::new(),
+            };
+            let pos = values.iter().position(|v| equals(v, arg));
+            pos.map_or(-1, |pos| pos as i64).into()
+        }
+        SelectFunction::Count
+        | SelectFunction::Sum
+        | SelectFunction::Min
+        | SelectFunction::Max
+        | SelectFunction::Avg => {
+            let mut aggregator = Aggregator::new(func)?;
+            if let Some(value) = target {
+                let elements = match container_of(func, value)? {
+                    Container::List(values) | Container::Set(values) => values,
+                    Container::Map(entries) => entries.into_iter().map(|(_, v)| v).collect(),
+                    Container::SortedSet(members) => {
+                        members.into_iter().map(|(_, s)| s.into()).collect()
+                    }
+                };
+                for element in elements {
+                    aggregator.push(element)?;
+                }
+            }
+            aggregator.finish()
+        }
+        SelectFunction::Sinter | SelectFunction::Sunion | SelectFunction::Sdiff => {
+            let mut members = match target {
+                Some(value) => set_of(func, value)?,
+                None => Vec::new(),
+            };
+            for arg in args {
+                members = combine(func, members, &arg_members(func, arg)?);
+            }
+            SetValue::from(members).into()
+        }
+        SelectFunction::Zscore | SelectFunction::Zrank => {
+            let arg = one_arg(func, args)?;
+            let members = match target {
+                Some(value) => sorted_set_of(func, value)?,
+                None => Vec::new(),
+            };
+            match members.iter().position(|(m, _)| equals(m, arg)) {
+                Some(rank) if func == SelectFunction::Zrank => (rank as i64).into(),
+                Some(rank) => members[rank].1.into(),
+                None => Value::default(),
+            }
+        }
+        SelectFunction::Slice | SelectFunction::Zrange | SelectFunction::ZrangeByScore => {
+            unreachable!()
+        }
+    };
+    Ok(value)
+}
+
+/// An accumulator of an aggregate select function.
+///
+/// Servers push the values of the evaluated objects to aggregate them across
+/// objects.
+#[derive(Clone, Debug)]
+pub struct Aggregator {
+    func: SelectFunction,
+    count: i64,
+    sum: Value,
+    extreme: Option<Value>,
+    members: Option<Vec<Value>>,
+}
+
+impl Aggregator {
+    /// Creates an aggregator for COUNT, SUM, MIN, MAX, AVG, SINTER, SUNION or
+    /// SDIFF.
+    pub fn new(func: SelectFunction) -> Result<Self> {
+        let sum = match func {
+            SelectFunction::Count
+            | SelectFunction::Sum
+            | SelectFunction::Min
+            | SelectFunction::Max
+            | SelectFunction::Sinter
+            | SelectFunction::Sunion
+            | SelectFunction::Sdiff => 0.into(),
+            SelectFunction::Avg => 0.0.into(),
+            _ => {
+                return Err(invalid_argument(format!(
+                    "{:?} is not an aggregate function",
+                    func
+                )))
+            }
+        };
+        Ok(Self {
+            func,
+            count: 0,
+            sum,
+            extreme: None,
+            members: None,
+        })
+    }
+
+    /// Adds a value to the aggregate.
+    ///
+    /// Unset and null values are ignored, except by set functions which treat
+    /// them as empty sets.
+    pub fn push(&mut self, value: Value) -> Result<()> {
+        let missing = matches!(value.value, None | Some(V::NullValue(_)));
+        if let SelectFunction::Sinter | SelectFunction::Sunion | SelectFunction::Sdiff = self.func {
+            let other = if missing {
+                Vec::new()
+            } else {
+                set_of(self.func, &value)?
+            };
+            self.members = Some(match self.members.take() {
+                Some(members) => combine(self.func, members, &other),
+                None => other,
+            });
+            self.count += 1;
+            return Ok(());
+        }
+        if missing {
+            return Ok(());
+        }
+        match self.func {
+            SelectFunction::Sum | SelectFunction::Avg => {
+                if !matches!(value.value, Some(V::I64Value(_) | V::F64Value(_))) {
+                    return Err(type_mismatch(self.func, &value));
+                }
+                self.sum = add(&self.sum, &value)?;
+            }
+            SelectFunction::Min | SelectFunction::Max => {
+                let replace = match &self.extreme {
+                    None => true,
+                    Some(extreme) => match compare(&value, extreme) {
+                        Some(ord) => {
+                            ord == Ordering::Less && self.func == SelectFunction::Min
+                                || ord == Ordering::Greater && self.func == SelectFunction::Max
+                        }
+                        None => {
+                            return Err(error(
+                                ObjectErrorCode::TypeMismatch,
+                                format!(
+                                    "{:?} can not compare {:?} with {:?}",
+                                    self.func,
+                                    value.value_type(),
+                                    extreme.value_type()
+                                ),
+                            ))
+                        }
+                    },
+                };
+                if replace {
+                    self.extreme = Some(value);
+                }
+            }
+            _ => {}
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Returns the aggregate of the pushed values.
+    pub fn finish(self) -> Value {
+        match self.func {
+            SelectFunction::Count => self.count.into(),
+            SelectFunction::Sum => self.sum,
+            SelectFunction::Min | SelectFunction::Max => self.extreme.unwrap_or_default(),
+            SelectFunction::Sinter | SelectFunction::Sunion | SelectFunction::Sdiff => {
+                SetValue::from(self.members.unwrap_or_default()).into()
+            }
+            _ => match self.sum.value {
+                Some(V::F64Value(sum)) if self.count > 0 => (sum / self.count as f64).into(),
+                _ => Value::default(),
+            },
+        }
+    }
+}
+
+/// Evaluates a mutate expression on an object, which is None if the object
+/// does not exist or is removed by the mutation.
+///
+/// The condition of the expression is not evaluated. On error, the object is
+/// left unchanged.
+pub fn apply_mutate(object: &mut Option<Value>, expr: &MutateExpr) -> Result<Value> {
+    let func = MutateFunction::from_i32(expr.func)
+        .ok_or_else(|| invalid_argument(format!("unknown mutate function {}", expr.func)))?;
+    *object = present(object.take());
+    match &expr.index {
+        Some(index) if func != MutateFunction::Trim => {
+            with_element(object, index, |element| mutate(element, func, expr))
+        }
+        _ => mutate(object, func, expr),
+    }
+}
+
+fn mutate(object: &mut Option<Value>, func: MutateFunction, expr: &MutateExpr) -> Result<Value> {
+    let args = expr.args.as_slice();
+    let output = match func {
+        MutateFunction::Expire | MutateFunction::Persist => {
+            return Err(invalid_argument(format!(
+                "{:?} is not a function of values",
+                func
+            )))
+        }
+        MutateFunction::Set => {
+            *object = present(Some(one_arg(func, args)?.clone()));
+            Value::default()
+        }
+        MutateFunction::Delete => {
+            no_args(func, args)?;
+            *object = None;
+            Value::default()
+        }
+        MutateFunction::Add => {
+            let delta = one_arg(func, args)?;
+            let sum = match object {
+                Some(value) => add(value, delta)?,
+                None => add(&0.into(), delta)?,
+            };
+            *object = Some(sum.clone());
+            sum
+        }
+        MutateFunction::Trim => {
+            no_args(func, args)?;
+            let range = expr
+                .index
+                .as_ref()
+                .ok_or_else(|| invalid_argument("TRIM expects a range index"))?;
+            if let Some(value) = object {
+                let values = list_of(func, value)?;
+                let range = positions(values.len(), range)?;
+                *object = Some(values[range].to_vec().into());
+            }
+            Value::default()
+        }
+        MutateFunction::Lpop | MutateFunction::Rpop => {
+            let count = pop_count(func, args)?;
+            let mut values = match object {
+                Some(value) => list_of(func, value)?,
+                None => Vec::new(),
+            };
+            let n = count.unwrap_or(1).min(values.len());
+            let popped: Vec<Value> = if func == MutateFunction::Lpop {
+                values.drain(..n).collect()
+            } else {
+                values.drain(values.len() - n..).rev().collect()
+            };
+            if object.is_some() {
+                *object = Some(values.into());
+            }
+            match count {
+                Some(_) => popped.into(),
+                None => popped.into_iter().next().unwrap_or_default(),
+            }
+        }
+        MutateFunction::Lpush | MutateFunction::Rpush => {
+            let mut values = match object {
+                Some(value) => list_of(func, value)?,
+                None => Vec::new(),
+            };
+            if func == MutateFunction::Lpush {
+                values.splice(0..0, args.iter().cloned());
+            } else {
+                values.extend(args.iter().cloned());
+            }
+            let len = values.len() as i64;
+            *object = Some(values.into());
+            len.into()
+        }
+        MutateFunction::Clear => {
+            no_args(func, args)?;
+            if let Some(value) = object {
+                *object = Some(container_of(func, value)?.clear().into_value());
+            }
+            Value::default()
+        }
+        MutateFunction::Extend => {
+            let other = one_arg(func, args)?;
+            let other = Container::from_value(other)?
+                .ok_or_else(|| invalid_argument("EXTEND expects a container argument"))?;
+            let container = match object {
+                Some(value) => container_of(func, value)?
+                    .extend(other)
+                    .ok_or_else(|| type_mismatch(func, value))?,
+                None => other,
+            };
+            let len = container.len() as i64;
+            *object = Some(container.into_value());
+            len.into()
+        }
+        MutateFunction::Remove => match object {
+            Some(value) => {
+                let mut container = container_of(func, value)?;
+                let removed = container.remove(args) as i64;
+                *object = Some(container.into_value());
+                removed.into()
+            }
+            None => 0.into(),
+        },
+        MutateFunction::SinterStore | MutateFunction::SunionStore | MutateFunction::SdiffStore => {
+            let op = match func {
+                MutateFunction::SinterStore => SelectFunction::Sinter,
+                MutateFunction::SunionStore => SelectFunction::Sunion,
+                _ => SelectFunction::Sdiff,
+            };
+            let mut members = match object {
+                Some(value) => set_of(func, value)?,
+                None => Vec::new(),
+            };
+            for arg in args {
+                members = combine(op, members, &arg_members(func, arg)?);
+            }
+            let len = members.len() as i64;
+            *object = Some(SetValue::from(members).into());
+            len.into()
+        }
+        MutateFunction::Spop => {
+            let count = pop_count(func, args)?;
+            let mut members = match object {
+                Some(value) => set_of(func, value)?,
+                None => Vec::new(),
+            };
+            let n = count.unwrap_or(1).min(members.len());
+            let popped: Vec<Value> = (0..n)
+                .map(|_| members.swap_remove(random_position(members.len())))
+                .collect();
+            if object.is_some() {
+                *object = Some(SetValue::from(members).into());
+            }
+            match count {
+                Some(_) => SetValue::from(popped).into(),
+                None => popped.into_iter().next().unwrap_or_default(),
+            }
+        }
+        MutateFunction::Zadd => {
+            let other = match Container::from_value(one_arg(func, args)?)? {
+                Some(Container::SortedSet(other)) => other,
+                _ => return Err(invalid_argument("ZADD expects a sorted set argument")),
+            };
+            let mut members = match object {
+                Some(value) => sorted_set_of(func, value)?,
+                None => Vec::new(),
+            };
+            let added = upsert_scored(&mut members, other) as i64;
+            *object = Some(Container::SortedSet(members).into_value());
+            added.into()
+        }
+        MutateFunction::Zincrby => {
+            let (member, increment) = match args {
+                [member, increment] => match increment.value {
+                    Some(V::I64Value(i)) => (member, i as f64),
+                    Some(V::F64Value(f)) if !f.is_nan() => (member, f),
+                    _ => return Err(invalid_argument("expect an i64 or f64 increment")),
+                },
+                _ => return Err(invalid_argument("ZINCRBY expects two arguments")),
+            };
+            let mut members = match object {
+                Some(value) => sorted_set_of(func, value)?,
+                None => Vec::new(),
+            };
+            let score = match members.iter_mut().find(|(m, _)| equals(m, member)) {
+                Some(entry) => {
+                    // Adding opposite infinities makes a NaN, which can not be
+                    // stored as a score.
+                    let score = entry.1 + increment;
+                    if score.is_nan() {
+                        return Err(error(
+                            ObjectErrorCode::OutOfRange,
+                            "ZINCRBY results in a NaN score",
+                        ));
+                    }
+                    entry.1 = score;
+                    score
+                }
+                None => {
+                    members.push((member.clone(), increment));
+                    increment
+                }
+            };
+            *object = Some(Container::SortedSet(members).into_value());
+            score.into()
+        }
+    };
+    Ok(output)
+}
+
+fn list_of(func: impl Debug, value: &Value) -> Result<Vec<Value>> {
+    match Container::from_value(value)? {
+        Some(Container::List(values)) => Ok(values),
+        _ => Err(type_mismatch(func, value)),
+    }
+}
+
+fn container_of(func: impl Debug, value: &Value) -> Result<Container> {
+    Container::from_value(value)?.ok_or_else(|| type_mismatch(func, value))
+}
+
+fn set_of(func: impl Debug, value: &Value) -> Result<Vec<Value>> {
+    match Container::from_value(value)? {
+        Some(Container::Set(members)) => Ok(members),
+        _ => Err(type_mismatch(func, value)),
+    }
+}
+
+fn sorted_set_of(func: impl Debug, value: &Value) -> Result<Vec<(Value, f64)>> {
+    match Container::from_value(value)? {
+        Some(Container::SortedSet(members)) => Ok(members),
+        _ => Err(type_mismatch(func, value)),
+    }
+}
+
+/// Returns the members of a set argument. Lists are accepted as sets of their
+/// elements.
+fn arg_members(func: impl Debug, arg: &Value) -> Result<Vec<Value>> {
+    match Container::from_value(arg)? {
+        Some(Container::Set(members)) => Ok(members),
+        Some(Container::List(values)) => Ok(combine(SelectFunction::Sunion, Vec::new(), &values)),
+        _ => Err(invalid_argument(format!(
+            "{:?} expects set arguments",
+            func
+        ))),
+    }
+}
+
+/// Parses the optional count argument of pop functions.
+fn pop_count(func: impl Debug, args: &[Value]) -> Result<Option<usize>> {
+    match args {
+        [] => Ok(None),
+        [count] => match count.value {
+            Some(V::I64Value(count)) if count >= 0 => Ok(Some(count as usize)),
+            _ => Err(invalid_argument("expect a non-negative i64 count")),
+        },
+        _ => Err(invalid_argument(format!(
+            "{:?} expects at most one argument",
+            func
+        ))),
+    }
+}
+
+/// Adds two numbers. Integer addition is checked, and adding an integer to a
+/// float results in a float.
+fn add(value: &Value, delta: &Value) -> Result<Value> {
+    let sum = match (&value.value, &delta.value) {
+        (Some(V::I64Value(a)), Some(V::I64Value(b))) => a
+            .checked_add(*b)
+            .ok_or_else(|| error(ObjectErrorCode::OutOfRange, "i64 addition overflow"))?
+            .into(),
+        (Some(V::I64Value(a)), Some(V::F64Value(b))) => (*a as f64 + b).into(),
+        (Some(V::F64Value(a)), Some(V::I64Value(b))) => (a + *b as f64).into(),
+        (Some(V::F64Value(a)), Some(V::F64Value(b))) => (a + b).into(),
+        (_, Some(V::I64Value(_) | V::F64Value(_))) => {
+            return Err(type_mismatch(MutateFunction::Add, value))
+        }
+        _ => return Err(invalid_argument("ADD expects an i64 or f64 argument")),
+    };
+    Ok(sum)
+}
+
+/// Evaluates a filter on an object.
+///
+/// VERSION and TTL operands are not supported, see [`eval_filter_with`].
+pub fn eval_filter(object: &Option<Value>, filter: &FilterExpr) -> Result<bool> {
+    eval_filter_with(filter, &|select| apply_select(object, select))
+}
+
+/// Evaluates a filter, resolving operands with the given function.
+///
+/// Servers use this to resolve operands that are not functions of values,
+/// such as VERSION and TTL.
+pub fn eval_filter_with(
+    filter: &FilterExpr,
+    operand: &dyn Fn(&SelectExpr) -> Result<Value>,
+) -> Result<bool> {
+    let func = FilterFunction::from_i32(filter.func)
+        .ok_or_else(|| invalid_argument(format!("unknown filter function {}", filter.func)))?;
+    match func {
+        FilterFunction::And => {
+            for expr in &filter.exprs {
+                if !eval_filter_with(expr, operand)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        FilterFunction::Or => {
+            for expr in &filter.exprs {
+                if eval_filter_with(expr, operand)? {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+        FilterFunction::Not => {
+            return match filter.exprs.as_slice() {
+                [expr] => Ok(!eval_filter_with(expr, operand)?),
+                _ => Err(invalid_argument("NOT expects one expression")),
+            };
+        }
+        _ => {}
+    }
+
+    let value = operand(&SelectExpr {
+        func: filter.select,
+        index: filter.index.clone(),
+        ..Default::default()
+    })?;
+    let args = filter.args.as_slice();
+    let ordered = |f: fn(Ordering) -> bool| -> Result<bool> {
+        Ok(compare(&value, one_arg(func, args)?).map_or(false, f))
+    };
+    match func {
+        FilterFunction::Eq => Ok(equals(&value, one_arg(func, args)?)),
+        FilterFunction::Ne => Ok(!equals(&value, one_arg(func, args)?)),
+        FilterFunction::Lt => ordered(Ordering::is_lt),
+        FilterFunction::Le => ordered(Ordering::is_le),
+        FilterFunction::Gt => ordered(Ordering::is_gt),
+        FilterFunction::Ge => ordered(Ordering::is_ge),
+        FilterFunction::In => Ok(args.iter().any(|arg| equals(&value, arg))),
+        FilterFunction::Prefix => Ok(match (&value.value, &one_arg(func, args)?.value) {
+            (Some(V::BlobValue(v)), Some(V::BlobValue(prefix))) => v.starts_with(prefix),
+            (Some(V::TextValue(v)), Some(V::TextValue(prefix))) => v.starts_with(prefix.as_str()),
+            _ => false,
+        }),
+        FilterFunction::IsType => match one_arg(func, args)?.value {
+            Some(V::I64Value(t)) => Ok(value.value_type() as i64 == t),
+            _ => Err(invalid_argument("IS_TYPE expects an i64 argument")),
+        },
+        FilterFunction::IsPresent => {
+            no_args(func, args)?;
+            Ok(value.value.is_some())
+        }
+        FilterFunction::And | FilterFunction::Or | FilterFunction::Not => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+    use crate::v1::filter::*;
+
+    fn mutate_expr(func: MutateFunction, args: impl IntoIterator<Item = Value>) -> MutateExpr {
+        MutateExpr {
+            func: func as i32,
+            args: args.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn select_expr(func: SelectFunction, args: impl IntoIterator<Item = Value>) -> SelectExpr {
+        SelectExpr {
+            func: func as i32,
+            args: args.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn code(result: Result<impl Debug>) -> ObjectErrorCode {
+        result.unwrap_err().code()
+    }
+
+    /// Applies a mutation if its condition holds, like servers do.
+    fn apply_conditional(object: &mut Option<Value>, expr: &MutateExpr) -> Result<Value> {
+        if let Some(condition) = &expr.condition {
+            if !eval_filter(object, condition)? {
+                return Err(error(ObjectErrorCode::ConditionFailed, "condition failed"));
+            }
+        }
+        apply_mutate(object, expr)
+    }
+
+    #[test]
+    fn scalar_select_and_mutate() {
+        let mut object = None;
+        assert_eq!(
+            apply_select(&object, &SelectExpr::get()).unwrap(),
+            Value::default()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::exists()).unwrap(),
+            false.into()
+        );
+        apply_mutate(&mut object, &MutateExpr::set("text")).unwrap();
+        assert_eq!(
+            apply_select(&object, &SelectExpr::get()).unwrap(),
+            "text".into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::value_type()).unwrap(),
+            (ValueType::Text as i64).into()
+        );
+        apply_mutate(&mut object, &MutateExpr::delete()).unwrap();
+        assert_eq!(object, None);
+    }
+
+    #[test]
+    fn numeric_add() {
+        let mut object = None;
+        assert_eq!(
+            apply_mutate(&mut object, &MutateExpr::add(2)).unwrap(),
+            2.into()
+        );
+        assert_eq!(
+            apply_mutate(&mut object, &MutateExpr::add(0.5)).unwrap(),
+            2.5.into()
+        );
+
+        let mut object = Some(i64::MAX.into());
+        assert_eq!(
+            code(apply_mutate(&mut object, &MutateExpr::add(1))),
+            ObjectErrorCode::OutOfRange
+        );
+        assert_eq!(object, Some(i64::MAX.into()));
+
+        let mut object = Some("text".into());
+        assert_eq!(
+            code(apply_mutate(&mut object, &MutateExpr::add(1))),
+            ObjectErrorCode::TypeMismatch
+        );
+        assert_eq!(
+            code(apply_mutate(&mut object, &MutateExpr::add("x"))),
+            ObjectErrorCode::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn list_select_and_mutate() {
+        let mut object = Some(vec![1i64, 2, 3].into());
+        assert_eq!(apply_select(&object, &SelectExpr::len()).unwrap(), 3.into());
+        assert_eq!(
+            apply_select(&object, &SelectExpr::get().with_index(-1)).unwrap(),
+            3.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::get().with_index(3)).unwrap(),
+            Value::default()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::slice(1..)).unwrap(),
+            vec![2i64, 3].into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::index_of(2)).unwrap(),
+            1.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::contains(4)).unwrap(),
+            false.into()
+        );
+
+        let rpush = mutate_expr(MutateFunction::Rpush, [4.into()]);
+        assert_eq!(apply_mutate(&mut object, &rpush).unwrap(), 4.into());
+        let lpop = mutate_expr(MutateFunction::Lpop, []);
+        assert_eq!(apply_mutate(&mut object, &lpop).unwrap(), 1.into());
+        apply_mutate(&mut object, &MutateExpr::add(10).with_index(0)).unwrap();
+        assert_eq!(object, Some(vec![12i64, 3, 4].into()));
+
+        assert_eq!(
+            code(apply_mutate(&mut object, &MutateExpr::set(0).with_index(3))),
+            ObjectErrorCode::OutOfRange
+        );
+        assert_eq!(
+            code(apply_select(&object, &SelectExpr::get().with_index("a"))),
+            ObjectErrorCode::InvalidArgument
+        );
+        assert_eq!(
+            code(apply_select(&object, &SelectExpr::keys())),
+            ObjectErrorCode::TypeMismatch
+        );
+        assert_eq!(object, Some(vec![12i64, 3, 4].into()));
+    }
+
+    #[test]
+    fn extreme_positions() {
+        use std::ops::Bound::*;
+
+        let object = Some(vec![1i64, 2, 3].into());
+        let slice = |range| apply_select(&object, &SelectExpr::slice(range)).unwrap();
+        let all = Value::from(vec![1i64, 2, 3]);
+        let none = Value::from(Vec::<i64>::new());
+        assert_eq!(slice((Included(0), Included(i64::MAX))), all);
+        assert_eq!(slice((Excluded(i64::MAX), Unbounded)), none);
+        assert_eq!(slice((Included(i64::MIN), Excluded(i64::MAX))), all);
+        assert_eq!(slice((Unbounded, Included(i64::MIN))), none);
+        assert_eq!(
+            slice((Excluded(i64::MIN), Included(-2))),
+            vec![1i64, 2].into()
+        );
+
+        let mut list = object.clone();
+        let trim = MutateExpr {
+            func: MutateFunction::Trim as i32,
+            index: Some(RangeValue::from_bounds((Excluded(0), Included(i64::MAX))).into()),
+            ..Default::default()
+        };
+        apply_mutate(&mut list, &trim).unwrap();
+        assert_eq!(list, Some(vec![2i64, 3].into()));
+
+        let set = Some(SortedSetValue::from(vec![("a".to_owned(), 1.0)]).into());
+        assert_eq!(
+            apply_select(&set, &SelectExpr::zrange(..=i64::MAX)).unwrap(),
+            set.clone().unwrap()
+        );
+    }
+
+    #[test]
+    fn map_select_and_mutate() {
+        let mut object = None;
+        apply_mutate(&mut object, &MutateExpr::set(1).with_index("a")).unwrap();
+        apply_mutate(&mut object, &MutateExpr::add(2).with_index("b")).unwrap();
+        let expected: BTreeMap<String, i64> = [("a".to_owned(), 1), ("b".to_owned(), 2)].into();
+        let map: BTreeMap<String, i64> = object.clone().unwrap().try_into().unwrap();
+        assert_eq!(map, expected);
+        assert_eq!(
+            apply_select(&object, &SelectExpr::get().with_index("b")).unwrap(),
+            2.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::contains("a")).unwrap(),
+            true.into()
+        );
+        assert_eq!(apply_select(&object, &SelectExpr::sum()).unwrap(), 3.into());
+
+        apply_mutate(&mut object, &MutateExpr::delete().with_index("a")).unwrap();
+        assert_eq!(apply_select(&object, &SelectExpr::len()).unwrap(), 1.into());
+        apply_mutate(&mut object, &MutateExpr::set("x").with_index("c")).unwrap();
+        assert_eq!(
+            code(apply_mutate(
+                &mut object,
+                &MutateExpr::add(1).with_index("c")
+            )),
+            ObjectErrorCode::TypeMismatch
+        );
+        assert_eq!(
+            code(apply_select(&object, &SelectExpr::index_of(2))),
+            ObjectErrorCode::TypeMismatch
+        );
+    }
+
+    #[test]
+    fn set_select_and_mutate() {
+        let set =
+            |members: &[i64]| -> Value { members.iter().copied().collect::<BTreeSet<_>>().into() };
+        let mut object = Some(set(&[1, 2, 3]));
+        assert_eq!(
+            apply_select(&object, &SelectExpr::sinter([vec![2i64, 3, 4]])).unwrap(),
+            set(&[2, 3])
+        );
+        assert_eq!(
+            apply_mutate(&mut object, &MutateExpr::sdiff_store([vec![1i64]])).unwrap(),
+            2.into()
+        );
+        let members: BTreeSet<i64> = object.clone().unwrap().try_into().unwrap();
+        assert_eq!(members, [2, 3].into());
+        let popped = apply_mutate(&mut object, &MutateExpr::spop()).unwrap();
+        assert!(popped == 2.into() || popped == 3.into());
+        assert_eq!(apply_select(&object, &SelectExpr::len()).unwrap(), 1.into());
+
+        assert_eq!(
+            code(apply_select(&object, &SelectExpr::get().with_index(0))),
+            ObjectErrorCode::TypeMismatch
+        );
+        assert_eq!(
+            code(apply_select(
+                &object,
+                &select_expr(SelectFunction::Sunion, [1.into()])
+            )),
+            ObjectErrorCode::InvalidArgument
+        );
+        let mut list = Some(vec![1i64].into());
+        assert_eq!(
+            code(apply_mutate(&mut list, &MutateExpr::spop())),
+            ObjectErrorCode::TypeMismatch
+        );
+    }
+
+    #[test]
+    fn sorted_set_select_and_mutate() {
+        let mut object = None;
+        assert_eq!(
+            apply_mutate(
+                &mut object,
+                &MutateExpr::zadd(vec![("a".to_owned(), 2.0), ("b".to_owned(), 1.0)])
+            )
+            .unwrap(),
+            2.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::zrank("a")).unwrap(),
+            1.into()
+        );
+        assert_eq!(
+            apply_mutate(&mut object, &MutateExpr::zincrby("b", 5.0)).unwrap(),
+            6.0.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::zscore("b")).unwrap(),
+            6.0.into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::zrange(0..1)).unwrap(),
+            SortedSetValue::from(vec![("a".to_owned(), 2.0)]).into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::zrange_by_score(5.0..)).unwrap(),
+            SortedSetValue::from(vec![("b".to_owned(), 6.0)]).into()
+        );
+        assert_eq!(
+            apply_select(&object, &SelectExpr::zscore("c")).unwrap(),
+            Value::default()
+        );
+
+        let zincrby = mutate_expr(MutateFunction::Zincrby, ["a".into(), "x".into()]);
+        assert_eq!(
+            code(apply_mutate(&mut object, &zincrby)),
+            ObjectErrorCode::InvalidArgument
+        );
+        apply_mutate(&mut object, &MutateExpr::zincrby("a", f64::INFINITY)).unwrap();
+        let before = object.clone();
+        assert_eq!(
+            code(apply_mutate(
+                &mut object,
+                &MutateExpr::zincrby("a", f64::NEG_INFINITY)
+            )),
+            ObjectErrorCode::OutOfRange
+        );
+        assert_eq!(object, before);
+        let mut text = Some("text".into());
+        assert_eq!(
+            code(apply_mutate(&mut text, &MutateExpr::zincrby("a", 1.0))),
+            ObjectErrorCode::TypeMismatch
+        );
+    }
+
+    #[test]
+    fn aggregates() {
+        let object = Some(vec![1i64, 5, 3].into());
+        let aggregate = |func| apply_select(&object, &select_expr(func, [])).unwrap();
+        assert_eq!(aggregate(SelectFunction::Count), 3.into());
+        assert_eq!(aggregate(SelectFunction::Min), 1.into());
+        assert_eq!(aggregate(SelectFunction::Max), 5.into());
+        assert_eq!(aggregate(SelectFunction::Avg), 3.0.into());
+
+        let object = Some(vec![Value::from(1), "a".into()].into());
+        assert_eq!(
+            code(apply_select(&object, &SelectExpr::sum())),
+            ObjectErrorCode::TypeMismatch
+        );
+    }
+
+    #[test]
+    fn conditional_mutations() {
+        let mut object = None;
+        apply_conditional(&mut object, &MutateExpr::set_if_absent(1)).unwrap();
+        assert_eq!(
+            code(apply_conditional(
+                &mut object,
+                &MutateExpr::set_if_absent(2)
+            )),
+            ObjectErrorCode::ConditionFailed
+        );
+        assert_eq!(
+            code(apply_conditional(
+                &mut object,
+                &MutateExpr::set_if_equals(2, 3)
+            )),
+            ObjectErrorCode::ConditionFailed
+        );
+        apply_conditional(&mut object, &MutateExpr::set_if_equals(1, 3)).unwrap();
+        assert_eq!(object, Some(3.into()));
+
+        let expr = MutateExpr::add(1).with_condition(value().lt(3));
+        assert_eq!(
+            code(apply_conditional(&mut object, &expr)),
+            ObjectErrorCode::ConditionFailed
+        );
+        apply_conditional(&mut object, &MutateExpr::delete_if_equals(3)).unwrap();
+        assert_eq!(object, None);
+    }
+
+    #[test]
+    fn filters() {
+        let object = Some(Value::from(MapValue::from([
+            ("name".to_owned(), Value::from("a")),
+            ("age".to_owned(), 3.into()),
+        ])));
+        assert!(eval_filter(&object, &field("age").ge(3)).unwrap());
+        assert!(eval_filter(&object, &field("name").has_prefix("a")).unwrap());
+        assert!(eval_filter(&object, &field("name").is_in(["a", "b"])).unwrap());
+        assert!(!eval_filter(&object, &field("name").lt(1)).unwrap());
+        assert!(eval_filter(&object, &field("age").is_type(ValueType::I64)).unwrap());
+        assert!(eval_filter(&object, &!field("email").is_present()).unwrap());
+        assert!(eval_filter(&None, &FilterExpr::default()).unwrap());
+    }
+
+    #[test]
+    fn compare_values() {
+        assert_eq!(compare(&1.into(), &1.5.into()), Some(Ordering::Less));
+        assert_eq!(compare(&"a".into(), &1.into()), None);
+        assert_eq!(
+            compare(&vec![1i64, 2].into(), &vec![1i64].into()),
+            Some(Ordering::Greater)
+        );
+        assert!(equals(
+            &Decimal {
+                value: "1.50".to_owned()
+            }
+            .into(),
+            &Decimal {
+                value: "1.5".to_owned()
+            }
+            .into()
+        ));
+    }
+}
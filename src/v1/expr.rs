@@ -0,0 +1,309 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::RangeBounds;
+
+use crate::v1::{filter::*, *};
+
+impl ObjectExpr {
+    pub fn batch<K: Into<Vec<u8>>>(keys: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            target: Some(object_expr::Target::Batch(BatchExpr {
+                keys: keys.into_iter().map(Into::into).collect(),
+            })),
+            ..Default::default()
+        }
+    }
+
+    pub fn range(range: impl Into<RangeExpr>) -> Self {
+        Self {
+            target: Some(object_expr::Target::Range(range.into())),
+            ..Default::default()
+        }
+    }
+
+    pub fn index(index: IndexExpr) -> Self {
+        Self {
+            target: Some(object_expr::Target::Index(index)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_filter(mut self, filter: FilterExpr) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn with_select(mut self, select: SelectExpr) -> Self {
+        self.select = Some(select);
+        self.mutate = None;
+        self.aggregate = false;
+        self
+    }
+
+    /// Aggregates the evaluated objects with an aggregate select function.
+    pub fn with_aggregate(mut self, select: SelectExpr) -> Self {
+        self.select = Some(select);
+        self.mutate = None;
+        self.aggregate = true;
+        self
+    }
+
+    pub fn with_mutate(mut self, mutate: MutateExpr) -> Self {
+        self.mutate = Some(mutate);
+        self.select = None;
+        self.aggregate = false;
+        self
+    }
+}
+
+impl SelectExpr {
+    pub fn get() -> Self {
+        Self::new(SelectFunction::Get, [])
+    }
+
+    pub fn len() -> Self {
+        Self::new(SelectFunction::Len, [])
+    }
+
+    pub fn version() -> Self {
+        Self::new(SelectFunction::Version, [])
+    }
+
+    pub fn exists() -> Self {
+        Self::new(SelectFunction::Exists, [])
+    }
+
+    /// Selects the [`ValueType`] of the value as an i64.
+    pub fn value_type() -> Self {
+        Self::new(SelectFunction::Type, [])
+    }
+
+    /// Selects whether a list has an element, a map has a key, or a set has a
+    /// member equal to the value.
+    pub fn contains(v: impl Into<Value>) -> Self {
+        Self::new(SelectFunction::Contains, [v.into()])
+    }
+
+    pub fn keys() -> Self {
+        Self::new(SelectFunction::Keys, [])
+    }
+
+    pub fn values() -> Self {
+        Self::new(SelectFunction::Values, [])
+    }
+
+    pub fn items() -> Self {
+        Self::new(SelectFunction::Items, [])
+    }
+
+    /// Selects the list elements in a range of positions.
+    ///
+    /// Negative positions count from the end, so `slice(-10..)` selects the
+    /// last ten elements.
+    pub fn slice(range: impl RangeBounds<i64>) -> Self {
+        Self::new(SelectFunction::Slice, []).with_index(RangeValue::from_bounds(range))
+    }
+
+    pub fn index_of(v: impl Into<Value>) -> Self {
+        Self::new(SelectFunction::IndexOf, [v.into()])
+    }
+
+    pub fn count() -> Self {
+        Self::new(SelectFunction::Count, [])
+    }
+
+    pub fn sum() -> Self {
+        Self::new(SelectFunction::Sum, [])
+    }
+
+    pub fn min() -> Self {
+        Self::new(SelectFunction::Min, [])
+    }
+
+    pub fn max() -> Self {
+        Self::new(SelectFunction::Max, [])
+    }
+
+    pub fn avg() -> Self {
+        Self::new(SelectFunction::Avg, [])
+    }
+
+    /// Selects the intersection of a set with the sets.
+    pub fn sinter<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(SelectFunction::Sinter, sets_to_values(sets))
+    }
+
+    /// Selects the union of a set with the sets.
+    pub fn sunion<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(SelectFunction::Sunion, sets_to_values(sets))
+    }
+
+    /// Selects the members of a set that are not in any of the sets.
+    pub fn sdiff<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(SelectFunction::Sdiff, sets_to_values(sets))
+    }
+
+    pub fn zscore(member: impl Into<Value>) -> Self {
+        Self::new(SelectFunction::Zscore, [member.into()])
+    }
+
+    pub fn zrank(member: impl Into<Value>) -> Self {
+        Self::new(SelectFunction::Zrank, [member.into()])
+    }
+
+    /// Selects the members of a sorted set in a range of ranks.
+    ///
+    /// Negative ranks count from the end, so `zrange(-3..)` selects the three
+    /// members with the highest scores.
+    pub fn zrange(ranks: impl RangeBounds<i64>) -> Self {
+        Self::new(SelectFunction::Zrange, []).with_index(RangeValue::from_bounds(ranks))
+    }
+
+    /// Selects the members of a sorted set in a range of scores.
+    pub fn zrange_by_score(scores: impl RangeBounds<f64>) -> Self {
+        Self::new(SelectFunction::ZrangeByScore, []).with_index(RangeValue::from_bounds(scores))
+    }
+
+    /// Selects the remaining time to live of the object as a duration, or
+    /// nothing if the object does not expire.
+    pub fn ttl() -> Self {
+        Self::new(SelectFunction::Ttl, [])
+    }
+
+    pub fn with_index(mut self, index: impl Into<Value>) -> Self {
+        self.index = Some(index.into());
+        self
+    }
+
+    /// Returns the version of each object along with its value.
+    pub fn with_version(mut self) -> Self {
+        self.with_version = true;
+        self
+    }
+
+    fn new(func: SelectFunction, args: impl IntoIterator<Item = Value>) -> Self {
+        Self {
+            func: func as i32,
+            args: args.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl MutateExpr {
+    pub fn set(v: impl Into<Value>) -> Self {
+        Self::new(MutateFunction::Set, [v.into()])
+    }
+
+    pub fn delete() -> Self {
+        Self::new(MutateFunction::Delete, [])
+    }
+
+    pub fn add(v: impl Into<Value>) -> Self {
+        Self::new(MutateFunction::Add, [v.into()])
+    }
+
+    /// Replaces a set with its intersection with the sets.
+    pub fn sinter_store<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(MutateFunction::SinterStore, sets_to_values(sets))
+    }
+
+    /// Replaces a set with its union with the sets.
+    pub fn sunion_store<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(MutateFunction::SunionStore, sets_to_values(sets))
+    }
+
+    /// Removes the members of the sets from a set.
+    pub fn sdiff_store<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Self {
+        Self::new(MutateFunction::SdiffStore, sets_to_values(sets))
+    }
+
+    /// Removes and returns a random member of a set.
+    pub fn spop() -> Self {
+        Self::new(MutateFunction::Spop, [])
+    }
+
+    /// Removes and returns up to `count` random members of a set.
+    pub fn spop_many(count: u64) -> Self {
+        Self::new(MutateFunction::Spop, [(count as i64).into()])
+    }
+
+    /// Adds members to a sorted set, replacing the scores of existing members.
+    pub fn zadd(members: impl Into<SortedSetValue>) -> Self {
+        Self::new(MutateFunction::Zadd, [members.into().into()])
+    }
+
+    pub fn zincrby(member: impl Into<Value>, increment: f64) -> Self {
+        Self::new(MutateFunction::Zincrby, [member.into(), increment.into()])
+    }
+
+    /// Expires the object after the time to live.
+    pub fn expire(ttl: impl Into<Duration>) -> Self {
+        Self::new(MutateFunction::Expire, [ttl.into().into()])
+    }
+
+    /// Expires the object at the deadline.
+    pub fn expire_at(deadline: impl Into<Timestamp>) -> Self {
+        Self::new(MutateFunction::Expire, [deadline.into().into()])
+    }
+
+    /// Removes the expiration of the object.
+    pub fn persist() -> Self {
+        Self::new(MutateFunction::Persist, [])
+    }
+
+    /// Sets the object to the value if the object does not exist.
+    pub fn set_if_absent(v: impl Into<Value>) -> Self {
+        Self::set(v).with_condition(!value().is_present())
+    }
+
+    /// Sets the object to the value if the object equals the expected value.
+    pub fn set_if_equals(expected: impl Into<Value>, v: impl Into<Value>) -> Self {
+        Self::set(v).with_condition(value().eq(expected))
+    }
+
+    /// Deletes the object if the object equals the expected value.
+    pub fn delete_if_equals(expected: impl Into<Value>) -> Self {
+        Self::delete().with_condition(value().eq(expected))
+    }
+
+    pub fn with_index(mut self, index: impl Into<Value>) -> Self {
+        self.index = Some(index.into());
+        self
+    }
+
+    pub fn with_condition(mut self, condition: FilterExpr) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Applies the mutation only if the object has the version.
+    pub fn with_expected_version(mut self, version: Version) -> Self {
+        self.expected_version = version;
+        self
+    }
+
+    fn new(func: MutateFunction, args: impl IntoIterator<Item = Value>) -> Self {
+        Self {
+            func: func as i32,
+            args: args.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+fn sets_to_values<S: Into<SetValue>>(sets: impl IntoIterator<Item = S>) -> Vec<Value> {
+    sets.into_iter().map(|set| set.into().into()).collect()
+}
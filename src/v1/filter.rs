@@ -0,0 +1,170 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builders to write [`FilterExpr`] as typed expressions.
+//!
+//! ```
+//! use engula_apis::v1::filter::*;
+//!
+//! let expr = field("age").ge(18) & !field("name").has_prefix("test");
+//! ```
+
+use std::ops::{BitAnd, BitOr, Not};
+
+use crate::v1::*;
+
+/// Returns an operand that evaluates the whole object value.
+pub fn value() -> Operand {
+    Operand::default()
+}
+
+/// Returns an operand that evaluates the element at the given map key or list
+/// index of the object value.
+pub fn field(index: impl Into<Value>) -> Operand {
+    Operand {
+        index: Some(index.into()),
+        ..Default::default()
+    }
+}
+
+/// Returns an operand that evaluates the length of the object value.
+pub fn len() -> Operand {
+    value().len()
+}
+
+/// Returns an operand that evaluates the version of the object.
+pub fn version() -> Operand {
+    Operand {
+        select: SelectFunction::Version,
+        ..Default::default()
+    }
+}
+
+/// Returns a filter that is true if all the given filters are true.
+pub fn and(exprs: impl IntoIterator<Item = FilterExpr>) -> FilterExpr {
+    logical(FilterFunction::And, exprs)
+}
+
+/// Returns a filter that is true if any of the given filters is true.
+pub fn or(exprs: impl IntoIterator<Item = FilterExpr>) -> FilterExpr {
+    logical(FilterFunction::Or, exprs)
+}
+
+/// Returns a filter that is true if the given filter is false.
+pub fn not(expr: FilterExpr) -> FilterExpr {
+    logical(FilterFunction::Not, [expr])
+}
+
+fn logical(func: FilterFunction, exprs: impl IntoIterator<Item = FilterExpr>) -> FilterExpr {
+    FilterExpr {
+        func: func as i32,
+        exprs: exprs.into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+/// The operand of a filter.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Operand {
+    index: Option<Value>,
+    select: SelectFunction,
+}
+
+impl Operand {
+    /// Returns an operand that evaluates the length of this operand.
+    pub fn len(mut self) -> Operand {
+        self.select = SelectFunction::Len;
+        self
+    }
+
+    pub fn eq(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Eq, [v.into()])
+    }
+
+    pub fn ne(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Ne, [v.into()])
+    }
+
+    pub fn lt(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Lt, [v.into()])
+    }
+
+    pub fn le(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Le, [v.into()])
+    }
+
+    pub fn gt(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Gt, [v.into()])
+    }
+
+    pub fn ge(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Ge, [v.into()])
+    }
+
+    /// Returns a filter that is true if the operand equals any of the given
+    /// values.
+    pub fn is_in<T: Into<Value>>(self, values: impl IntoIterator<Item = T>) -> FilterExpr {
+        self.func(FilterFunction::In, values.into_iter().map(Into::into))
+    }
+
+    /// Returns a filter that is true if the operand is a blob or text that
+    /// starts with the given prefix.
+    pub fn has_prefix(self, v: impl Into<Value>) -> FilterExpr {
+        self.func(FilterFunction::Prefix, [v.into()])
+    }
+
+    /// Returns a filter that is true if the operand is of the given type.
+    pub fn is_type(self, t: ValueType) -> FilterExpr {
+        self.func(FilterFunction::IsType, [Value::from(t as i64)])
+    }
+
+    /// Returns a filter that is true if the operand exists.
+    pub fn is_present(self) -> FilterExpr {
+        self.func(FilterFunction::IsPresent, [])
+    }
+
+    fn func(self, func: FilterFunction, args: impl IntoIterator<Item = Value>) -> FilterExpr {
+        FilterExpr {
+            func: func as i32,
+            args: args.into_iter().collect(),
+            index: self.index,
+            select: self.select as i32,
+            ..Default::default()
+        }
+    }
+}
+
+impl Not for FilterExpr {
+    type Output = FilterExpr;
+
+    fn not(self) -> Self::Output {
+        not(self)
+    }
+}
+
+impl BitAnd for FilterExpr {
+    type Output = FilterExpr;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        and([self, rhs])
+    }
+}
+
+impl BitOr for FilterExpr {
+    type Output = FilterExpr;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        or([self, rhs])
+    }
+}
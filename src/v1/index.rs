@@ -0,0 +1,96 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::v1::*;
+
+impl IndexOptions {
+    /// Returns the options of an index over a field of map values.
+    pub fn field(name: impl Into<String>) -> Self {
+        Self {
+            field: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options of an index over the whole object values.
+    pub fn value() -> Self {
+        Self::default()
+    }
+
+    /// Indexes each member of set and sorted set values.
+    pub fn with_members(mut self, members: bool) -> Self {
+        self.members = members;
+        self
+    }
+
+    pub fn with_unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
+}
+
+impl IndexExpr {
+    /// Looks up the objects indexed by any of the values.
+    ///
+    /// ```
+    /// use engula_apis::v1::*;
+    ///
+    /// let expr = ObjectExpr::index(IndexExpr::lookup("by_email", ["alice@example.com"]))
+    ///     .with_select(SelectExpr::get());
+    /// ```
+    pub fn lookup<V: Into<Value>>(
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            values: values.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Looks up the objects indexed by values in the range.
+    pub fn range(name: impl Into<String>, range: impl Into<RangeValue>) -> Self {
+        Self {
+            name: name.into(),
+            range: Some(range.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Looks up all the indexed objects.
+    pub fn all(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+}
+
+impl CollectionOptions {
+    pub fn with_index(mut self, name: impl Into<String>, options: IndexOptions) -> Self {
+        self.indexes.insert(name.into(), options);
+        self
+    }
+}
@@ -12,117 +12,317 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use prost::Message;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::Hash,
+};
 
 use crate::v1::*;
 
-macro_rules! impl_type {
-    ($rust_type:ty, $list_value:ident) => {
-        impl From<Vec<$rust_type>> for ListValue {
-            fn from(v: Vec<$rust_type>) -> Self {
-                Self {
-                    $list_value: v,
-                    ..Default::default()
-                }
-            }
-        }
+/// Types that can be the elements of a [`ListValue`].
+///
+/// Lists of primitive types are encoded in the typed fields of [`ListValue`],
+/// and lists of other types are encoded as generic values.
+pub trait ListElement: Into<Value> + Sized {
+    /// The type name of a list of this type, used in conversion errors.
+    const LIST_TYPE: &'static str = "list";
 
-        impl From<Vec<$rust_type>> for value::Value {
-            fn from(v: Vec<$rust_type>) -> Self {
-                ListValue::from(v).into()
-            }
-        }
+    fn into_list(v: Vec<Self>) -> ListValue {
+        ListValue::from_values(v.into_iter().map(Into::into).collect())
+    }
 
-        impl From<&'_ [$rust_type]> for ListValue {
-            fn from(v: &'_ [$rust_type]) -> Self {
-                Vec::from(v).into()
-            }
-        }
+    fn try_from_list(v: ListValue) -> Result<Vec<Self>, ConversionError> {
+        try_from_values(v.into_values()?)
+    }
+
+    fn try_from_value(v: Value) -> Result<Self, ConversionError>;
+}
 
-        impl From<&'_ [$rust_type]> for value::Value {
-            fn from(v: &'_ [$rust_type]) -> Self {
-                ListValue::from(v).into()
+pub(crate) fn try_from_values<T: ListElement>(
+    values: Vec<Value>,
+) -> Result<Vec<T>, ConversionError> {
+    let mut result = Vec::with_capacity(values.len());
+    let mut iter = values.into_iter();
+    while let Some(v) = iter.next() {
+        match T::try_from_value(v) {
+            Ok(v) => result.push(v),
+            Err(err) => {
+                let index = result.len();
+                return Err(err.at(format!("[{}]", index)).map_value(|v| {
+                    let values = result
+                        .into_iter()
+                        .map(Into::into)
+                        .chain(Some(v))
+                        .chain(iter)
+                        .collect();
+                    ListValue {
+                        values,
+                        ..Default::default()
+                    }
+                    .into()
+                }));
             }
         }
+    }
+    Ok(result)
+}
 
-        impl<const N: usize> From<[$rust_type; N]> for ListValue {
-            fn from(v: [$rust_type; N]) -> Self {
-                Vec::from(v).into()
-            }
+impl ListValue {
+    /// Creates a list of values.
+    ///
+    /// If all values are of the same primitive type, the list is encoded in
+    /// the corresponding typed field.
+    pub fn from_values(values: Vec<Value>) -> Self {
+        macro_rules! pack {
+            ($value_type:path, $list_value:ident) => {
+                if values
+                    .iter()
+                    .all(|v| matches!(v.value, Some($value_type(_))))
+                {
+                    let $list_value = values
+                        .into_iter()
+                        .filter_map(|v| match v.value {
+                            Some($value_type(v)) => Some(v),
+                            _ => None,
+                        })
+                        .collect();
+                    return Self {
+                        $list_value,
+                        ..Default::default()
+                    };
+                }
+            };
         }
 
-        impl<const N: usize> From<[$rust_type; N]> for value::Value {
-            fn from(v: [$rust_type; N]) -> Self {
-                ListValue::from(v).into()
-            }
+        if values.is_empty() {
+            return Self::default();
         }
+        pack!(value::Value::I64Value, i64_value);
+        pack!(value::Value::F64Value, f64_value);
+        pack!(value::Value::BlobValue, blob_value);
+        pack!(value::Value::TextValue, text_value);
+        pack!(value::Value::BoolValue, bool_value);
+        pack!(value::Value::NullValue, null_value);
+        pack!(value::Value::TimestampValue, timestamp_value);
+        pack!(value::Value::DurationValue, duration_value);
+        pack!(value::Value::DecimalValue, decimal_value);
+        Self {
+            values,
+            ..Default::default()
+        }
+    }
 
-        impl<const N: usize> From<&'_ [$rust_type; N]> for ListValue {
-            fn from(v: &'_ [$rust_type; N]) -> Self {
-                v.as_slice().into()
-            }
+    /// Returns the elements of the list as generic values.
+    ///
+    /// Returns an error if more than one field of the list is set.
+    pub fn into_values(self) -> Result<Vec<Value>, ConversionError> {
+        fn convert<T: Into<value::Value>>(v: Vec<T>) -> Vec<Value> {
+            v.into_iter().map(|v| Value::from(v.into())).collect()
         }
 
-        impl<const N: usize> From<&'_ [$rust_type; N]> for value::Value {
-            fn from(v: &'_ [$rust_type; N]) -> Self {
-                ListValue::from(v).into()
-            }
+        let len = self.len();
+        if self.values.len() == len {
+            Ok(self.values)
+        } else if self.i64_value.len() == len {
+            Ok(convert(self.i64_value))
+        } else if self.f64_value.len() == len {
+            Ok(convert(self.f64_value))
+        } else if self.blob_value.len() == len {
+            Ok(convert(self.blob_value))
+        } else if self.text_value.len() == len {
+            Ok(convert(self.text_value))
+        } else if self.bool_value.len() == len {
+            Ok(convert(self.bool_value))
+        } else if self.null_value.len() == len {
+            Ok(self.null_value.into_iter().map(|_| Value::null()).collect())
+        } else if self.timestamp_value.len() == len {
+            Ok(convert(self.timestamp_value))
+        } else if self.duration_value.len() == len {
+            Ok(convert(self.duration_value))
+        } else if self.decimal_value.len() == len {
+            Ok(convert(self.decimal_value))
+        } else {
+            Err(ConversionError::new("list", self))
         }
+    }
+
+    /// Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.i64_value.len()
+            + self.f64_value.len()
+            + self.blob_value.len()
+            + self.text_value.len()
+            + self.bool_value.len()
+            + self.null_value.len()
+            + self.timestamp_value.len()
+            + self.duration_value.len()
+            + self.decimal_value.len()
+            + self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+macro_rules! impl_element {
+    ($rust_type:ty, $list_value:ident, $type_name:literal) => {
+        impl ListElement for $rust_type {
+            const LIST_TYPE: &'static str = $type_name;
 
-        impl TryFrom<ListValue> for Vec<$rust_type> {
-            type Error = ListValue;
+            fn into_list(v: Vec<Self>) -> ListValue {
+                ListValue {
+                    $list_value: v,
+                    ..Default::default()
+                }
+            }
 
-            fn try_from(v: ListValue) -> Result<Self, Self::Error> {
-                if !v.$list_value.is_empty() || v.encoded_len() == 0 {
+            fn try_from_list(v: ListValue) -> Result<Vec<Self>, ConversionError> {
+                if v.$list_value.len() == v.len() {
                     Ok(v.$list_value)
+                } else if v.values.len() == v.len() {
+                    try_from_values(v.values)
                 } else {
-                    Err(v)
+                    Err(ConversionError::new($type_name, v))
                 }
             }
+
+            fn try_from_value(v: Value) -> Result<Self, ConversionError> {
+                v.try_into()
+            }
         }
+    };
+}
 
-        impl TryFrom<value::Value> for Vec<$rust_type> {
-            type Error = value::Value;
+impl_element!(i64, i64_value, "list<i64>");
+impl_element!(f64, f64_value, "list<f64>");
+impl_element!(Vec<u8>, blob_value, "list<blob>");
+impl_element!(String, text_value, "list<text>");
+impl_element!(bool, bool_value, "list<bool>");
+impl_element!(Timestamp, timestamp_value, "list<timestamp>");
+impl_element!(Duration, duration_value, "list<duration>");
+impl_element!(Decimal, decimal_value, "list<decimal>");
 
-            fn try_from(v: value::Value) -> Result<Self, Self::Error> {
-                if let value::Value::ListValue(v) = v {
-                    v.try_into().map_err(|v| value::Value::from(v))
-                } else {
-                    Err(v)
-                }
+impl ListElement for Value {
+    fn into_list(v: Vec<Self>) -> ListValue {
+        ListValue::from_values(v)
+    }
+
+    fn try_from_list(v: ListValue) -> Result<Vec<Self>, ConversionError> {
+        v.into_values()
+    }
+
+    fn try_from_value(v: Value) -> Result<Self, ConversionError> {
+        Ok(v)
+    }
+}
+
+macro_rules! impl_nested {
+    ($rust_type:ty, $($bound:tt)*) => {
+        impl<$($bound)*> ListElement for $rust_type {
+            fn try_from_value(v: Value) -> Result<Self, ConversionError> {
+                v.try_into()
             }
         }
+    };
+}
 
-        impl TryFrom<Value> for Vec<$rust_type> {
-            type Error = Value;
+impl_nested!(Vec<T>, T: ListElement);
+impl_nested!(HashMap<K, V>, K: ListElement + Eq + Hash, V: ListElement);
+impl_nested!(BTreeMap<K, V>, K: ListElement + Ord, V: ListElement);
+impl_nested!(HashSet<T>, T: ListElement + Eq + Hash);
+impl_nested!(BTreeSet<T>, T: ListElement + Ord);
 
-            fn try_from(v: Value) -> Result<Self, Self::Error> {
-                if let Some(v) = v.value {
-                    v.try_into().map_err(|v| Value::from(v))
-                } else {
-                    Err(v)
-                }
-            }
+impl<T: ListElement> From<Vec<T>> for ListValue {
+    fn from(v: Vec<T>) -> Self {
+        T::into_list(v)
+    }
+}
+
+impl<T: ListElement> From<Vec<T>> for value::Value {
+    fn from(v: Vec<T>) -> Self {
+        ListValue::from(v).into()
+    }
+}
+
+impl<T: ListElement + Clone> From<&'_ [T]> for ListValue {
+    fn from(v: &'_ [T]) -> Self {
+        Vec::from(v).into()
+    }
+}
+
+impl<T: ListElement + Clone> From<&'_ [T]> for value::Value {
+    fn from(v: &'_ [T]) -> Self {
+        ListValue::from(v).into()
+    }
+}
+
+impl<T: ListElement, const N: usize> From<[T; N]> for ListValue {
+    fn from(v: [T; N]) -> Self {
+        Vec::from(v).into()
+    }
+}
+
+impl<T: ListElement, const N: usize> From<[T; N]> for value::Value {
+    fn from(v: [T; N]) -> Self {
+        ListValue::from(v).into()
+    }
+}
+
+impl<T: ListElement + Clone, const N: usize> From<&'_ [T; N]> for ListValue {
+    fn from(v: &'_ [T; N]) -> Self {
+        v.as_slice().into()
+    }
+}
+
+impl<T: ListElement + Clone, const N: usize> From<&'_ [T; N]> for value::Value {
+    fn from(v: &'_ [T; N]) -> Self {
+        ListValue::from(v).into()
+    }
+}
+
+impl<T: ListElement> TryFrom<ListValue> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(v: ListValue) -> Result<Self, Self::Error> {
+        T::try_from_list(v)
+    }
+}
+
+impl<T: ListElement> TryFrom<value::Value> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(v: value::Value) -> Result<Self, Self::Error> {
+        if let value::Value::ListValue(v) = v {
+            v.try_into()
+        } else {
+            Err(ConversionError::new(T::LIST_TYPE, v))
         }
+    }
+}
 
-        impl TryFrom<Value> for Option<Vec<$rust_type>> {
-            type Error = Value;
+impl<T: ListElement> TryFrom<Value> for Vec<T> {
+    type Error = ConversionError;
 
-            fn try_from(v: Value) -> Result<Self, Self::Error> {
-                if let Some(v) = v.value {
-                    v.try_into().map(Some).map_err(|v| Value::from(v))
-                } else {
-                    Ok(None)
-                }
-            }
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        if let Some(v) = v.value {
+            v.try_into()
+        } else {
+            Err(ConversionError::new(T::LIST_TYPE, v))
         }
-    };
+    }
 }
 
-impl_type!(i64, i64_value);
-impl_type!(f64, f64_value);
-impl_type!(Vec<u8>, blob_value);
-impl_type!(String, text_value);
+impl<T: ListElement> TryFrom<Value> for Option<Vec<T>> {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v.value {
+            None | Some(value::Value::NullValue(_)) => Ok(None),
+            Some(v) => v.try_into().map(Some),
+        }
+    }
+}
 
 impl<const N: usize> From<[i32; N]> for ListValue {
     fn from(v: [i32; N]) -> Self {
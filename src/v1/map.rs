@@ -12,9 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
 
-use crate::v1::*;
+use crate::v1::{list::try_from_values, *};
 
 impl<K, V> From<(K, V)> for MapValue
 where
@@ -30,27 +34,75 @@ where
 }
 
 impl TryFrom<MapValue> for (ListValue, ListValue) {
-    type Error = MapValue;
+    type Error = ConversionError;
 
     fn try_from(v: MapValue) -> Result<Self, Self::Error> {
         match (v.keys, v.values) {
             (Some(keys), Some(values)) => Ok((keys, values)),
-            (Some(keys), None) => Err(MapValue {
-                keys: Some(keys),
-                values: None,
-            }),
-            (None, Some(values)) => Err(MapValue {
-                keys: None,
-                values: Some(values),
-            }),
-            (None, None) => Err(MapValue::default()),
+            (keys, values) => {
+                let segment = if keys.is_none() { "keys" } else { "values" };
+                Err(ConversionError::new("list", Value::default())
+                    .at(segment)
+                    .with_value(MapValue { keys, values }))
+            }
         }
     }
 }
 
+impl MapValue {
+    fn try_into_entries<K, V>(self) -> Result<(Vec<K>, Vec<V>), ConversionError>
+    where
+        K: ListElement,
+        V: ListElement,
+    {
+        let (keys, values) = self.try_into()?;
+        let keys: Vec<K> = match try_from_entries(keys) {
+            Ok(keys) => keys,
+            Err(err) => {
+                return Err(err.at("keys").map_value(|keys| {
+                    MapValue {
+                        keys: keys.try_into().ok(),
+                        values: Some(values),
+                    }
+                    .into()
+                }))
+            }
+        };
+        let values: Vec<V> = match try_from_entries(values) {
+            Ok(values) => values,
+            Err(err) => {
+                return Err(err.at("values").map_value(|values| {
+                    MapValue {
+                        keys: Some(keys.into()),
+                        values: values.try_into().ok(),
+                    }
+                    .into()
+                }))
+            }
+        };
+        // Reports the first entry that misses its key or its value.
+        let missing = match keys.len().cmp(&values.len()) {
+            Ordering::Equal => return Ok((keys, values)),
+            Ordering::Less => ("map key", "keys", keys.len()),
+            Ordering::Greater => ("map value", "values", values.len()),
+        };
+        let (expected, segment, index) = missing;
+        Err(ConversionError::new(expected, Value::default())
+            .at(format!("[{}]", index))
+            .at(segment)
+            .with_value(MapValue::from((keys, values))))
+    }
+}
+
+/// Converts the keys or the values of a map element by element, so that an
+/// error reports the position of the entry that fails.
+fn try_from_entries<T: ListElement>(list: ListValue) -> Result<Vec<T>, ConversionError> {
+    try_from_values(list.into_values()?)
+}
+
 macro_rules! impl_map {
-    ($map_type:ty, $key_type:ty, $value_type:ty) => {
-        impl From<$map_type> for MapValue {
+    ($map_type:ty, $($bound:tt)*) => {
+        impl<$($bound)*> From<$map_type> for MapValue {
             fn from(map: $map_type) -> Self {
                 let mut keys = Vec::with_capacity(map.len());
                 let mut values = Vec::with_capacity(map.len());
@@ -62,100 +114,95 @@ macro_rules! impl_map {
             }
         }
 
-        impl From<$map_type> for value::Value {
+        impl<$($bound)*> From<$map_type> for value::Value {
             fn from(map: $map_type) -> Self {
                 MapValue::from(map).into()
             }
         }
 
-        impl TryFrom<MapValue> for $map_type {
-            type Error = MapValue;
+        impl<$($bound)*> TryFrom<MapValue> for $map_type {
+            type Error = ConversionError;
 
             fn try_from(v: MapValue) -> Result<Self, Self::Error> {
-                let (keys, values) = v.try_into()?;
-                let keys: Result<Vec<$key_type>, _> = keys.try_into();
-                let values: Result<Vec<$value_type>, _> = values.try_into();
-                match (keys, values) {
-                    (Ok(keys), Ok(values)) => {
-                        if keys.len() == values.len() {
-                            Ok(keys.into_iter().zip(values.into_iter()).collect())
-                        } else {
-                            Err((keys, values).into())
-                        }
-                    }
-                    (Ok(keys), Err(values)) => Err((keys, values).into()),
-                    (Err(keys), Ok(values)) => Err((keys, values).into()),
-                    (Err(keys), Err(values)) => Err((keys, values).into()),
-                }
+                let (keys, values) = v.try_into_entries()?;
+                Ok(keys.into_iter().zip(values.into_iter()).collect())
             }
         }
 
-        impl TryFrom<value::Value> for $map_type {
-            type Error = value::Value;
+        impl<$($bound)*> TryFrom<value::Value> for $map_type {
+            type Error = ConversionError;
 
             fn try_from(v: value::Value) -> Result<Self, Self::Error> {
                 if let value::Value::MapValue(v) = v {
-                    v.try_into().map_err(|v| value::Value::from(v))
+                    v.try_into()
                 } else {
-                    Err(v)
+                    Err(ConversionError::new("map", v))
                 }
             }
         }
 
-        impl TryFrom<Value> for $map_type {
-            type Error = Value;
+        impl<$($bound)*> TryFrom<Value> for $map_type {
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
                 if let Some(v) = v.value {
-                    v.try_into().map_err(|v| Value::from(v))
+                    v.try_into()
                 } else {
-                    Err(v)
+                    Err(ConversionError::new("map", v))
                 }
             }
         }
 
-        impl TryFrom<Value> for Option<$map_type> {
-            type Error = Value;
+        impl<$($bound)*> TryFrom<Value> for Option<$map_type> {
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
-                if let Some(v) = v.value {
-                    v.try_into().map(Some).map_err(|v| Value::from(v))
-                } else {
-                    Ok(None)
+                match v.value {
+                    None | Some(value::Value::NullValue(_)) => Ok(None),
+                    Some(v) => v.try_into().map(Some),
                 }
             }
         }
     };
 }
 
-macro_rules! impl_type {
-    ($key_type:ty, $value_type:ty) => {
-        impl_map!(HashMap<$key_type, $value_type>, $key_type, $value_type);
-        impl_map!(BTreeMap<$key_type, $value_type>, $key_type, $value_type);
+impl_map!(HashMap<K, V>, K: ListElement + Eq + Hash, V: ListElement);
+impl_map!(BTreeMap<K, V>, K: ListElement + Ord, V: ListElement);
 
-        impl<const N: usize> From<[($key_type, $value_type); N]> for MapValue {
-            fn from(map: [($key_type, $value_type); N]) -> Self {
-                HashMap::from(map).into()
-            }
-        }
+impl<K: ListElement, V: ListElement, const N: usize> From<[(K, V); N]> for MapValue {
+    fn from(map: [(K, V); N]) -> Self {
+        let (keys, values): (Vec<K>, Vec<V>) = map.into_iter().unzip();
+        (keys, values).into()
+    }
+}
 
-        impl<const N: usize> From<[($key_type, $value_type); N]> for value::Value {
-            fn from(map: [($key_type, $value_type); N]) -> Self {
-                MapValue::from(map).into()
-            }
-        }
+impl<K: ListElement, V: ListElement, const N: usize> From<[(K, V); N]> for value::Value {
+    fn from(map: [(K, V); N]) -> Self {
+        MapValue::from(map).into()
     }
 }
 
-impl_type!(i64, i64);
-impl_type!(i64, f64);
-impl_type!(i64, Vec<u8>);
-impl_type!(i64, String);
-impl_type!(Vec<u8>, i64);
-impl_type!(Vec<u8>, f64);
-impl_type!(Vec<u8>, Vec<u8>);
-impl_type!(Vec<u8>, String);
-impl_type!(String, i64);
-impl_type!(String, f64);
-impl_type!(String, Vec<u8>);
-impl_type!(String, String);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn entry_errors() {
+        let map = MapValue::from((texts(&["a", "b"]), vec![Value::from(1), "x".into()]));
+        let err = BTreeMap::<String, i64>::try_from(map).unwrap_err();
+        assert_eq!(err.to_string(), "expect i64, got text at values[1]");
+
+        let map = MapValue::from((texts(&["a", "b"]), texts(&["x", "y"])));
+        let err = BTreeMap::<String, i64>::try_from(map).unwrap_err();
+        assert_eq!(err.to_string(), "expect i64, got text at values[0]");
+
+        let map = MapValue::from((texts(&["a", "b", "c"]), vec![1i64]));
+        let err = BTreeMap::<String, i64>::try_from(map).unwrap_err();
+        assert_eq!(err.to_string(), "expect map value, got none at values[1]");
+        assert_eq!(err.path(), ["values", "[1]"]);
+    }
+}
@@ -15,10 +15,38 @@
 #![allow(clippy::all)]
 
 mod any;
+mod batch;
 mod bool;
+mod client;
+#[cfg(feature = "rust_decimal")]
+mod decimal;
+mod error;
+pub mod eval;
+mod expr;
+pub mod filter;
+mod index;
 mod list;
 mod map;
 mod range;
+mod read;
+mod result;
+mod scan;
+mod schema;
+#[cfg(feature = "server")]
+pub mod server;
 mod set;
+mod time;
+mod watch;
+
+pub use self::{
+    batch::Batch,
+    error::{ConversionError, ResponseError, ScanError},
+    list::ListElement,
+    range::KeyBound,
+    result::{BatchResult, BatchShape, Version},
+    scan::ScanStream,
+    schema::{i64_from_key, i64_key, timestamp_from_key, timestamp_key, Schema},
+    set::SortedSet,
+};
 
 tonic::include_proto!("engula.v1");
@@ -12,8 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::ops::{
-    Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+use std::{
+    ops::{
+        Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+    },
+    time::{self, SystemTime},
 };
 
 use crate::v1::*;
@@ -36,8 +39,22 @@ impl From<RangeFull> for RangeValue {
     }
 }
 
-macro_rules! impl_type {
-    ($rust_type:ty, $range_type:path) => {
+impl From<range_bound::Value> for value::Value {
+    fn from(v: range_bound::Value) -> Self {
+        match v {
+            range_bound::Value::I64Value(v) => Self::I64Value(v),
+            range_bound::Value::F64Value(v) => Self::F64Value(v),
+            range_bound::Value::BlobValue(v) => Self::BlobValue(v),
+            range_bound::Value::TextValue(v) => Self::TextValue(v),
+            range_bound::Value::TimestampValue(v) => Self::TimestampValue(v),
+            range_bound::Value::DurationValue(v) => Self::DurationValue(v),
+            range_bound::Value::DecimalValue(v) => Self::DecimalValue(v),
+        }
+    }
+}
+
+macro_rules! impl_bound {
+    ($rust_type:ty, $range_type:path, $type_name:literal) => {
         impl From<$rust_type> for range_bound::Value {
             fn from(v: $rust_type) -> Self {
                 $range_type(v)
@@ -45,17 +62,44 @@ macro_rules! impl_type {
         }
 
         impl TryFrom<range_bound::Value> for $rust_type {
-            type Error = range_bound::Value;
+            type Error = ConversionError;
 
             fn try_from(v: range_bound::Value) -> Result<Self, Self::Error> {
                 if let $range_type(v) = v {
                     Ok(v)
                 } else {
-                    Err(v)
+                    Err(ConversionError::new($type_name, value::Value::from(v)))
                 }
             }
         }
 
+        impl_type!($rust_type, $range_type, $type_name);
+    };
+    ($rust_type:ty => $bound_type:ty, $range_type:path, $type_name:literal) => {
+        impl From<$rust_type> for range_bound::Value {
+            fn from(v: $rust_type) -> Self {
+                $range_type(<$bound_type>::from(v))
+            }
+        }
+
+        impl TryFrom<range_bound::Value> for $rust_type {
+            type Error = ConversionError;
+
+            fn try_from(v: range_bound::Value) -> Result<Self, Self::Error> {
+                if let $range_type(v) = v {
+                    v.try_into()
+                } else {
+                    Err(ConversionError::new($type_name, value::Value::from(v)))
+                }
+            }
+        }
+
+        impl_type!($rust_type, $range_type, $type_name);
+    };
+}
+
+macro_rules! impl_type {
+    ($rust_type:ty, $range_type:path, $type_name:literal) => {
         impl From<Bound<$rust_type>> for RangeBound {
             fn from(v: Bound<$rust_type>) -> Self {
                 match v {
@@ -73,11 +117,12 @@ macro_rules! impl_type {
         }
 
         impl TryFrom<RangeBound> for Bound<$rust_type> {
-            type Error = RangeBound;
+            type Error = ConversionError;
 
             fn try_from(b: RangeBound) -> Result<Self, Self::Error> {
                 match b.value {
-                    Some($range_type(v)) => {
+                    Some(v @ $range_type(_)) => {
+                        let v = v.try_into()?;
                         if b.included {
                             Ok(Bound::Included(v))
                         } else {
@@ -85,7 +130,7 @@ macro_rules! impl_type {
                         }
                     }
                     None => Ok(Bound::Unbounded),
-                    _ => Err(b),
+                    Some(v) => Err(ConversionError::new($type_name, value::Value::from(v))),
                 }
             }
         }
@@ -170,49 +215,169 @@ macro_rules! impl_type {
         }
 
         impl TryFrom<RangeValue> for (Bound<$rust_type>, Bound<$rust_type>) {
-            type Error = ();
+            type Error = ConversionError;
 
             fn try_from(r: RangeValue) -> Result<Self, Self::Error> {
-                let start = if let Some(v) = r.start {
-                    v.try_into().map_err(|_| ())?
-                } else {
-                    Bound::Unbounded
+                let mismatch = [("start", &r.start), ("end", &r.end)].into_iter().find_map(
+                    |(segment, bound)| match bound {
+                        Some(RangeBound { value: Some(v), .. }) if !matches!(v, $range_type(_)) => {
+                            Some((segment, value::Value::from(v.clone())))
+                        }
+                        _ => None,
+                    },
+                );
+                if let Some((segment, v)) = mismatch {
+                    return Err(ConversionError::new($type_name, v)
+                        .at(segment)
+                        .with_value(r));
+                }
+                let start = match r.start {
+                    Some(v) => v.try_into()?,
+                    None => Bound::Unbounded,
                 };
-                let end = if let Some(v) = r.end {
-                    v.try_into().map_err(|_| ())?
-                } else {
-                    Bound::Unbounded
+                let end = match r.end {
+                    Some(v) => v.try_into()?,
+                    None => Bound::Unbounded,
                 };
                 Ok((start, end))
             }
         }
 
         impl TryFrom<value::Value> for (Bound<$rust_type>, Bound<$rust_type>) {
-            type Error = ();
+            type Error = ConversionError;
 
             fn try_from(v: value::Value) -> Result<Self, Self::Error> {
                 if let value::Value::RangeValue(v) = v {
                     v.try_into()
                 } else {
-                    Err(())
+                    Err(ConversionError::new(concat!("range<", $type_name, ">"), v))
                 }
             }
         }
 
         impl TryFrom<Value> for (Bound<$rust_type>, Bound<$rust_type>) {
-            type Error = ();
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
                 if let Some(v) = v.value {
                     v.try_into()
                 } else {
-                    Err(())
+                    Err(ConversionError::new(concat!("range<", $type_name, ">"), v))
                 }
             }
         }
     };
 }
 
-impl_type!(i64, range_bound::Value::I64Value);
-impl_type!(Vec<u8>, range_bound::Value::BlobValue);
-impl_type!(String, range_bound::Value::TextValue);
+impl_bound!(i64, range_bound::Value::I64Value, "i64");
+impl_bound!(f64, range_bound::Value::F64Value, "f64");
+impl_bound!(Vec<u8>, range_bound::Value::BlobValue, "blob");
+impl_bound!(String, range_bound::Value::TextValue, "text");
+impl_bound!(Timestamp, range_bound::Value::TimestampValue, "timestamp");
+impl_bound!(Duration, range_bound::Value::DurationValue, "duration");
+impl_bound!(Decimal, range_bound::Value::DecimalValue, "decimal");
+impl_bound!(SystemTime => Timestamp, range_bound::Value::TimestampValue, "timestamp");
+impl_bound!(time::Duration => Duration, range_bound::Value::DurationValue, "duration");
+#[cfg(feature = "chrono")]
+impl_bound!(chrono::DateTime<chrono::Utc> => Timestamp, range_bound::Value::TimestampValue, "timestamp");
+#[cfg(feature = "chrono")]
+impl_bound!(chrono::Duration => Duration, range_bound::Value::DurationValue, "duration");
+#[cfg(feature = "rust_decimal")]
+impl_bound!(rust_decimal::Decimal => Decimal, range_bound::Value::DecimalValue, "decimal");
+
+/// Types that can be the bounds of a [`RangeExpr`].
+///
+/// Keys are compared as bytes, so only the types that servers encode as keys
+/// can be key bounds.
+pub trait KeyBound: Into<range_bound::Value> + Clone {}
+
+impl KeyBound for i64 {}
+impl KeyBound for Vec<u8> {}
+impl KeyBound for String {}
+impl KeyBound for Timestamp {}
+impl KeyBound for SystemTime {}
+#[cfg(feature = "chrono")]
+impl KeyBound for chrono::DateTime<chrono::Utc> {}
+
+fn key_bound<T: KeyBound>(bound: Bound<T>) -> RangeBound {
+    match bound {
+        Bound::Included(v) => RangeBound {
+            value: Some(v.into()),
+            included: true,
+        },
+        Bound::Excluded(v) => RangeBound {
+            value: Some(v.into()),
+            included: false,
+        },
+        Bound::Unbounded => RangeBound::default(),
+    }
+}
+
+impl RangeExpr {
+    pub fn from_bounds<T: KeyBound>(range: impl RangeBounds<T>) -> Self {
+        (range.start_bound().cloned(), range.end_bound().cloned()).into()
+    }
+
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+}
+
+impl<T: KeyBound> From<(Bound<T>, Bound<T>)> for RangeExpr {
+    fn from(r: (Bound<T>, Bound<T>)) -> Self {
+        Self {
+            start: Some(key_bound(r.0)),
+            end: Some(key_bound(r.1)),
+            ..Default::default()
+        }
+    }
+}
+
+impl<T: KeyBound> From<Range<T>> for RangeExpr {
+    fn from(r: Range<T>) -> Self {
+        (Bound::Included(r.start), Bound::Excluded(r.end)).into()
+    }
+}
+
+impl<T: KeyBound> From<RangeFrom<T>> for RangeExpr {
+    fn from(r: RangeFrom<T>) -> Self {
+        (Bound::Included(r.start), Bound::Unbounded).into()
+    }
+}
+
+impl<T: KeyBound> From<RangeInclusive<T>> for RangeExpr {
+    fn from(r: RangeInclusive<T>) -> Self {
+        let (start, end) = r.into_inner();
+        (Bound::Included(start), Bound::Included(end)).into()
+    }
+}
+
+impl<T: KeyBound> From<RangeTo<T>> for RangeExpr {
+    fn from(r: RangeTo<T>) -> Self {
+        (Bound::Unbounded, Bound::Excluded(r.end)).into()
+    }
+}
+
+impl<T: KeyBound> From<RangeToInclusive<T>> for RangeExpr {
+    fn from(r: RangeToInclusive<T>) -> Self {
+        (Bound::Unbounded, Bound::Included(r.end)).into()
+    }
+}
+
+impl From<RangeFull> for RangeExpr {
+    fn from(_: RangeFull) -> Self {
+        Self::default()
+    }
+}
+
+impl From<RangeExpr> for object_expr::Target {
+    fn from(r: RangeExpr) -> Self {
+        Self::Range(r)
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::v1::*;
+
+impl ReadOptions {
+    /// Reads all changes committed before the request.
+    pub fn strong() -> Self {
+        Self::default()
+    }
+
+    /// Reads changes committed up to the staleness bound.
+    pub fn bounded_staleness(max_staleness: impl Into<Duration>) -> Self {
+        Self {
+            consistency: ReadConsistency::BoundedStaleness as i32,
+            max_staleness: Some(max_staleness.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Reads the snapshot at the sequence number.
+    pub fn snapshot(sequence: u64) -> Self {
+        Self {
+            consistency: ReadConsistency::Snapshot as i32,
+            snapshot_sequence: sequence,
+            ..Default::default()
+        }
+    }
+
+    /// Reads at least the changes up to the sequence number, usually the one
+    /// observed by a previous response.
+    pub fn with_min_sequence(mut self, sequence: u64) -> Self {
+        self.min_sequence = sequence;
+        self
+    }
+}
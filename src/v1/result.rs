@@ -0,0 +1,245 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::v1::*;
+
+/// The version of an object, which increases whenever the object changes.
+/// Objects that do not exist have version zero.
+pub type Version = u64;
+
+/// The shape of a [`BatchRequest`], used to decode its [`BatchResponse`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchShape {
+    num_universes: usize,
+    num_exprs: Vec<Vec<usize>>,
+    positions: Vec<(usize, usize, usize)>,
+}
+
+impl BatchShape {
+    pub(crate) fn with_positions(
+        req: &BatchRequest,
+        positions: Vec<(usize, usize, usize)>,
+    ) -> Self {
+        Self {
+            num_universes: req.universes.len(),
+            num_exprs: req
+                .databases
+                .iter()
+                .map(|db| db.requests.iter().map(|co| co.exprs.len()).collect())
+                .collect(),
+            positions,
+        }
+    }
+
+    /// Returns the number of expressions in the request.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Decodes a response of the request.
+    pub fn decode(&self, resp: BatchResponse) -> Result<BatchResult, ResponseError> {
+        if resp.universes.len() != self.num_universes {
+            return Err(ResponseError::ShapeMismatch(format!(
+                "expect {} universe responses, got {}",
+                self.num_universes,
+                resp.universes.len()
+            )));
+        }
+        if resp.databases.len() != self.num_exprs.len() {
+            return Err(ResponseError::ShapeMismatch(format!(
+                "expect {} database responses, got {}",
+                self.num_exprs.len(),
+                resp.databases.len()
+            )));
+        }
+        let sequence = resp.databases.iter().map(|db| db.sequence).max();
+        let mut results = Vec::with_capacity(self.num_exprs.len());
+        for (i, (db, num_exprs)) in resp.databases.into_iter().zip(&self.num_exprs).enumerate() {
+            if db.responses.len() != num_exprs.len() {
+                return Err(ResponseError::ShapeMismatch(format!(
+                    "expect {} collection responses in database {}, got {}",
+                    num_exprs.len(),
+                    i,
+                    db.responses.len()
+                )));
+            }
+            let mut db_results = Vec::with_capacity(num_exprs.len());
+            for (j, (co, &n)) in db.responses.into_iter().zip(num_exprs).enumerate() {
+                if co.results.len() != n {
+                    return Err(ResponseError::ShapeMismatch(format!(
+                        "expect {} results in collection {} of database {}, got {}",
+                        n,
+                        j,
+                        i,
+                        co.results.len()
+                    )));
+                }
+                db_results.push(co.results.into_iter().map(Some).collect::<Vec<_>>());
+            }
+            results.push(db_results);
+        }
+        Ok(BatchResult {
+            universes: resp.universes,
+            sequence: sequence.unwrap_or_default(),
+            results: self
+                .positions
+                .iter()
+                .map(|&(i, j, k)| results[i][j][k].take())
+                .collect(),
+        })
+    }
+}
+
+impl From<&BatchRequest> for BatchShape {
+    /// Returns the shape of a request, where expressions are numbered in the
+    /// order they appear in the request.
+    fn from(req: &BatchRequest) -> Self {
+        let mut positions = Vec::new();
+        for (i, db) in req.databases.iter().enumerate() {
+            for (j, co) in db.requests.iter().enumerate() {
+                positions.extend((0..co.exprs.len()).map(|k| (i, j, k)));
+            }
+        }
+        Self::with_positions(req, positions)
+    }
+}
+
+/// A decoded [`BatchResponse`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchResult {
+    universes: Vec<UniverseResponse>,
+    sequence: u64,
+    results: Vec<Option<ObjectResult>>,
+}
+
+impl BatchResult {
+    /// Returns the number of expression results.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Returns the latest sequence number observed by the database requests,
+    /// which can be passed to [`ReadOptions`] to chain causally consistent
+    /// reads.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Takes the universe responses.
+    pub fn take_universes(&mut self) -> Vec<UniverseResponse> {
+        std::mem::take(&mut self.universes)
+    }
+
+    /// Takes the raw result of the nth expression.
+    pub fn take_result(&mut self, n: usize) -> Result<ObjectResult, ResponseError> {
+        self.results
+            .get_mut(n)
+            .and_then(Option::take)
+            .ok_or(ResponseError::InvalidIndex(n))
+    }
+
+    /// Takes the values of the nth expression and converts them to `T`.
+    pub fn take_all<T>(&mut self, n: usize) -> Result<Vec<T>, ResponseError>
+    where
+        T: TryFrom<Value, Error = ConversionError>,
+    {
+        let values = self
+            .take_result(n)?
+            .into_result()
+            .map_err(ResponseError::Object)?;
+        values
+            .into_iter()
+            .map(|v| T::try_from(v).map_err(ResponseError::Conversion))
+            .collect()
+    }
+
+    /// Takes the value of the nth expression and converts it to `T`.
+    ///
+    /// The expression must have exactly one value.
+    pub fn take<T>(&mut self, n: usize) -> Result<T, ResponseError>
+    where
+        T: TryFrom<Value, Error = ConversionError>,
+    {
+        let mut values = self
+            .take_result(n)?
+            .into_result()
+            .map_err(ResponseError::Object)?;
+        if values.len() != 1 {
+            return Err(ResponseError::ShapeMismatch(format!(
+                "expect 1 value for expression {}, got {}",
+                n,
+                values.len()
+            )));
+        }
+        T::try_from(values.pop().unwrap()).map_err(ResponseError::Conversion)
+    }
+
+    /// Takes the values and versions of the nth expression and converts the
+    /// values to `T`.
+    ///
+    /// The select of the expression must ask for versions.
+    pub fn take_all_versioned<T>(&mut self, n: usize) -> Result<Vec<(T, Version)>, ResponseError>
+    where
+        T: TryFrom<Value, Error = ConversionError>,
+    {
+        let result = self.take_result(n)?;
+        let versions = result.versions.clone();
+        let values = result.into_result().map_err(ResponseError::Object)?;
+        if versions.len() != values.len() {
+            return Err(ResponseError::ShapeMismatch(format!(
+                "expect {} versions for expression {}, got {}",
+                values.len(),
+                n,
+                versions.len()
+            )));
+        }
+        values
+            .into_iter()
+            .zip(versions)
+            .map(|(v, version)| {
+                T::try_from(v)
+                    .map(|v| (v, version))
+                    .map_err(ResponseError::Conversion)
+            })
+            .collect()
+    }
+
+    /// Takes the value and version of the nth expression and converts the
+    /// value to `T`.
+    ///
+    /// The expression must have exactly one value, and its select must ask
+    /// for versions.
+    pub fn take_versioned<T>(&mut self, n: usize) -> Result<(T, Version), ResponseError>
+    where
+        T: TryFrom<Value, Error = ConversionError>,
+    {
+        let mut values = self.take_all_versioned(n)?;
+        if values.len() != 1 {
+            return Err(ResponseError::ShapeMismatch(format!(
+                "expect 1 value for expression {}, got {}",
+                n,
+                values.len()
+            )));
+        }
+        Ok(values.pop().unwrap())
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    vec,
+};
+
+use futures_core::{ready, Stream};
+use tonic::Streaming;
+
+use crate::v1::*;
+
+impl ScanRequest {
+    /// Creates a request to scan a whole collection.
+    pub fn new(dbname: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            dbname: dbname.into(),
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_range(mut self, range: impl Into<RangeExpr>) -> Self {
+        self.range = Some(range.into());
+        self
+    }
+
+    pub fn with_filter(mut self, filter: FilterExpr) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn with_select(mut self, select: SelectExpr) -> Self {
+        self.select = Some(select);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_resume_token(mut self, token: impl Into<Vec<u8>>) -> Self {
+        self.resume_token = token.into();
+        self
+    }
+}
+
+/// A stream of the objects of a scan, with values converted to `T`.
+///
+/// The stream yields the objects of each [`ScanResponse`] one by one. After
+/// an error, the scan can be continued with a new request that carries
+/// [`ScanStream::resume_token`].
+pub struct ScanStream<T> {
+    inner: Streaming<ScanResponse>,
+    objects: vec::IntoIter<(Vec<u8>, Value)>,
+    chunk_token: Option<Vec<u8>>,
+    resume_token: Vec<u8>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ScanStream<T> {
+    pub fn new(inner: Streaming<ScanResponse>) -> Self {
+        Self {
+            inner,
+            objects: Vec::new().into_iter(),
+            chunk_token: None,
+            resume_token: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the token to resume the scan after the last chunk whose
+    /// objects have all been yielded.
+    ///
+    /// Objects of a partially yielded chunk are yielded again on resume.
+    pub fn resume_token(&self) -> &[u8] {
+        &self.resume_token
+    }
+}
+
+impl<T> Stream for ScanStream<T>
+where
+    T: TryFrom<Value, Error = ConversionError>,
+{
+    type Item = Result<(Vec<u8>, T), ScanError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some((key, value)) = self.objects.next() {
+                let item = T::try_from(value)
+                    .map(|value| (key, value))
+                    .map_err(ScanError::Conversion);
+                return Poll::Ready(Some(item));
+            }
+            if let Some(token) = self.chunk_token.take() {
+                self.resume_token = token;
+            }
+            match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(resp)) => {
+                    self.objects = resp
+                        .keys
+                        .into_iter()
+                        .zip(resp.values)
+                        .collect::<Vec<_>>()
+                        .into_iter();
+                    self.chunk_token = Some(resp.resume_token);
+                }
+                Some(Err(status)) => return Poll::Ready(Some(Err(ScanError::Status(status)))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
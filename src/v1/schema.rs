@@ -0,0 +1,325 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    time::{self, SystemTime},
+};
+
+use crate::v1::*;
+
+/// A Rust type that declares the schema of its values.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use engula_apis::v1::*;
+///
+/// let options = CollectionOptions::default()
+///     .with_key_type(ValueType::Text)
+///     .with_value_schema(ValueSchema::of::<HashMap<String, Vec<i64>>>());
+/// ```
+pub trait Schema {
+    fn schema() -> ValueSchema;
+}
+
+impl ValueSchema {
+    /// Returns a schema that allows any value.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Returns the schema of values of the type, with any elements.
+    pub fn new(value_type: ValueType) -> Self {
+        Self {
+            value_type: value_type as i32,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the schema of the Rust type.
+    pub fn of<T: Schema>() -> Self {
+        T::schema()
+    }
+
+    pub fn list(element: ValueSchema) -> Self {
+        Self::new(ValueType::List).with_element(element)
+    }
+
+    pub fn set(member: ValueSchema) -> Self {
+        Self::new(ValueType::Set).with_element(member)
+    }
+
+    pub fn sorted_set(member: ValueSchema) -> Self {
+        Self::new(ValueType::SortedSet).with_element(member)
+    }
+
+    pub fn map(key: ValueSchema, value: ValueSchema) -> Self {
+        Self {
+            key: Some(Box::new(key)),
+            ..Self::new(ValueType::Map).with_element(value)
+        }
+    }
+
+    /// Returns the schema of maps with the fields.
+    pub fn fields<K: Into<String>>(fields: impl IntoIterator<Item = (K, ValueSchema)>) -> Self {
+        Self {
+            fields: fields.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            ..Self::new(ValueType::Map)
+        }
+    }
+
+    fn with_element(mut self, element: ValueSchema) -> Self {
+        self.element = Some(Box::new(element));
+        self
+    }
+
+    /// Checks that the value matches the schema.
+    ///
+    /// The error locates the first element that does not match.
+    pub fn check(&self, v: &Value) -> Result<(), ConversionError> {
+        let expected = self.value_type();
+        if expected == ValueType::Unspecified {
+            return Ok(());
+        }
+        if v.value_type() != expected {
+            return Err(ConversionError::new(type_label(expected), v.clone()));
+        }
+        let element = self.element.as_deref();
+        let result = match &v.value {
+            Some(value::Value::ListValue(list)) => check_elements(element, list),
+            Some(value::Value::SetValue(set)) => match &set.keys {
+                Some(keys) => check_elements(element, keys).map_err(|err| err.at("keys")),
+                None => Ok(()),
+            },
+            Some(value::Value::SortedSetValue(set)) => match &set.members {
+                Some(members) => check_elements(element, members).map_err(|err| err.at("members")),
+                None => Ok(()),
+            },
+            Some(value::Value::MapValue(map)) => self.check_map(map),
+            _ => Ok(()),
+        };
+        result.map_err(|err| err.with_value(v.clone()))
+    }
+
+    fn check_map(&self, map: &MapValue) -> Result<(), ConversionError> {
+        let (keys, values) = <(ListValue, ListValue)>::try_from(map.clone())?;
+        check_elements(self.key.as_deref(), &keys).map_err(|err| err.at("keys"))?;
+        check_elements(self.element.as_deref(), &values).map_err(|err| err.at("values"))?;
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+        let entries = keys.into_values()?.into_iter().zip(values.into_values()?);
+        for (index, (k, v)) in entries.enumerate() {
+            let name = match k.value {
+                Some(value::Value::TextValue(name)) => name,
+                _ => {
+                    return Err(ConversionError::new("text", k)
+                        .at(format!("[{}]", index))
+                        .at("keys"))
+                }
+            };
+            match self.fields.get(&name) {
+                Some(field) => field.check(&v),
+                // Fields that are not in the schema must not exist.
+                None => Err(ConversionError::new("none", v)),
+            }
+            .map_err(|err| err.at(name))?;
+        }
+        Ok(())
+    }
+}
+
+fn check_elements(schema: Option<&ValueSchema>, list: &ListValue) -> Result<(), ConversionError> {
+    let schema = match schema {
+        Some(schema) if schema.value_type() != ValueType::Unspecified => schema,
+        _ => return Ok(()),
+    };
+    for (index, v) in list.clone().into_values()?.iter().enumerate() {
+        schema
+            .check(v)
+            .map_err(|err| err.at(format!("[{}]", index)))?;
+    }
+    Ok(())
+}
+
+fn type_label(value_type: ValueType) -> &'static str {
+    match value_type {
+        ValueType::Unspecified => "any",
+        ValueType::I64 => "i64",
+        ValueType::F64 => "f64",
+        ValueType::Blob => "blob",
+        ValueType::Text => "text",
+        ValueType::Bool => "bool",
+        ValueType::Null => "null",
+        ValueType::Timestamp => "timestamp",
+        ValueType::Duration => "duration",
+        ValueType::Decimal => "decimal",
+        ValueType::List => "list",
+        ValueType::Map => "map",
+        ValueType::Set => "set",
+        ValueType::SortedSet => "sorted_set",
+        ValueType::Range => "range",
+    }
+}
+
+impl CollectionOptions {
+    pub fn with_default_ttl(mut self, ttl: impl Into<Duration>) -> Self {
+        self.default_ttl = Some(ttl.into());
+        self
+    }
+
+    pub fn with_key_type(mut self, key_type: ValueType) -> Self {
+        self.key_type = key_type as i32;
+        self
+    }
+
+    pub fn with_value_schema(mut self, schema: ValueSchema) -> Self {
+        self.value_schema = Some(schema);
+        self
+    }
+}
+
+/// Encodes a key of a collection with i64 keys.
+pub fn i64_key(v: i64) -> Vec<u8> {
+    ((v as u64) ^ (1 << 63)).to_be_bytes().to_vec()
+}
+
+/// Decodes a key of a collection with i64 keys, or returns None if the key is
+/// not 8 bytes.
+pub fn i64_from_key(key: &[u8]) -> Option<i64> {
+    let bytes = <[u8; 8]>::try_from(key).ok()?;
+    Some((u64::from_be_bytes(bytes) ^ (1 << 63)) as i64)
+}
+
+/// Encodes a key of a collection with timestamp keys, which is the i64 key of
+/// the seconds followed by the nanos in big-endian order, or returns None if
+/// the nanos are out of range.
+pub fn timestamp_key(v: &Timestamp) -> Option<Vec<u8>> {
+    if !(0..1_000_000_000).contains(&v.nanos) {
+        return None;
+    }
+    let mut key = i64_key(v.seconds);
+    key.extend_from_slice(&(v.nanos as u32).to_be_bytes());
+    Some(key)
+}
+
+/// Decodes a key of a collection with timestamp keys, or returns None if the
+/// key is not 12 bytes or the nanos are out of range.
+pub fn timestamp_from_key(key: &[u8]) -> Option<Timestamp> {
+    if key.len() != 12 {
+        return None;
+    }
+    let seconds = i64_from_key(&key[..8])?;
+    let nanos = u32::from_be_bytes(key[8..].try_into().ok()?);
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+    Some(Timestamp {
+        seconds,
+        nanos: nanos as i32,
+    })
+}
+
+macro_rules! impl_schema {
+    ($rust_type:ty, $value_type:ident) => {
+        impl Schema for $rust_type {
+            fn schema() -> ValueSchema {
+                ValueSchema::new(ValueType::$value_type)
+            }
+        }
+    };
+}
+
+impl_schema!(i64, I64);
+impl_schema!(f64, F64);
+impl_schema!(Vec<u8>, Blob);
+impl_schema!(String, Text);
+impl_schema!(bool, Bool);
+impl_schema!(Timestamp, Timestamp);
+impl_schema!(Duration, Duration);
+impl_schema!(Decimal, Decimal);
+impl_schema!(SystemTime, Timestamp);
+impl_schema!(time::Duration, Duration);
+#[cfg(feature = "chrono")]
+impl_schema!(chrono::DateTime<chrono::Utc>, Timestamp);
+#[cfg(feature = "chrono")]
+impl_schema!(chrono::Duration, Duration);
+#[cfg(feature = "rust_decimal")]
+impl_schema!(rust_decimal::Decimal, Decimal);
+impl_schema!(ListValue, List);
+impl_schema!(MapValue, Map);
+impl_schema!(SetValue, Set);
+impl_schema!(SortedSetValue, SortedSet);
+impl_schema!(RangeValue, Range);
+
+impl Schema for Value {
+    fn schema() -> ValueSchema {
+        ValueSchema::any()
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> ValueSchema {
+        ValueSchema::list(T::schema())
+    }
+}
+
+impl<T: Schema> Schema for HashSet<T> {
+    fn schema() -> ValueSchema {
+        ValueSchema::set(T::schema())
+    }
+}
+
+impl<T: Schema> Schema for BTreeSet<T> {
+    fn schema() -> ValueSchema {
+        ValueSchema::set(T::schema())
+    }
+}
+
+impl<K: Schema> Schema for SortedSet<K> {
+    fn schema() -> ValueSchema {
+        ValueSchema::sorted_set(K::schema())
+    }
+}
+
+impl<K: Schema, V: Schema> Schema for HashMap<K, V> {
+    fn schema() -> ValueSchema {
+        ValueSchema::map(K::schema(), V::schema())
+    }
+}
+
+impl<K: Schema, V: Schema> Schema for BTreeMap<K, V> {
+    fn schema() -> ValueSchema {
+        ValueSchema::map(K::schema(), V::schema())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_keys() {
+        let ts = |seconds, nanos| Timestamp { seconds, nanos };
+        let ordered = [ts(-1, 999_999_999), ts(0, 0), ts(0, 1), ts(1, 0)];
+        let keys: Vec<_> = ordered.iter().map(|t| timestamp_key(t).unwrap()).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        for (t, key) in ordered.iter().zip(&keys) {
+            assert_eq!(timestamp_from_key(key).as_ref(), Some(t));
+        }
+        assert_eq!(timestamp_key(&ts(0, -1)), None);
+        assert_eq!(timestamp_key(&ts(0, 1_000_000_000)), None);
+    }
+}
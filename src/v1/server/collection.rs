@@ -0,0 +1,858 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::{Bound, RangeBounds},
+    time::{self, SystemTime},
+};
+
+use prost::Message;
+use tonic::Status;
+
+use super::index::Index;
+use crate::v1::{
+    eval::{
+        self, apply_mutate, apply_select, eval_filter_with, invalid_argument, Aggregator, Result,
+    },
+    *,
+};
+
+#[derive(Clone)]
+struct Object {
+    value: Value,
+    version: u64,
+    deadline: Option<SystemTime>,
+}
+
+impl Object {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.deadline.map_or(false, |deadline| deadline <= now)
+    }
+}
+
+/// Approximate bytes to store the version and the deadline of an object.
+const OBJECT_OVERHEAD_BYTES: u64 = 24;
+
+/// Statistics of a set of objects.
+#[derive(Clone, Default)]
+struct Statistics {
+    num_objects: u64,
+    key_bytes: u64,
+    value_bytes: u64,
+    /// The number of objects and value bytes of each value type.
+    value_types: BTreeMap<ValueType, (u64, u64)>,
+}
+
+impl Statistics {
+    fn add(&mut self, key: &[u8], value: &Value) {
+        let value_bytes = value.encoded_len() as u64;
+        self.num_objects += 1;
+        self.key_bytes += key.len() as u64;
+        self.value_bytes += value_bytes;
+        let entry = self.value_types.entry(value.value_type()).or_default();
+        entry.0 += 1;
+        entry.1 += value_bytes;
+    }
+
+    fn remove(&mut self, key: &[u8], value: &Value) {
+        let value_bytes = value.encoded_len() as u64;
+        self.num_objects -= 1;
+        self.key_bytes -= key.len() as u64;
+        self.value_bytes -= value_bytes;
+        if let Some(entry) = self.value_types.get_mut(&value.value_type()) {
+            entry.0 -= 1;
+            entry.1 -= value_bytes;
+            if entry.0 == 0 {
+                self.value_types.remove(&value.value_type());
+            }
+        }
+    }
+}
+
+/// A change committed to an object.
+///
+/// The object before the change is kept to revert the change when reading a
+/// snapshot.
+#[derive(Clone)]
+pub(super) struct Change {
+    pub(super) collection_id: u64,
+    pub(super) event: ChangeEvent,
+    old_object: Option<Object>,
+}
+
+impl Change {
+    /// Returns the event of the change, with the old value if requested.
+    pub(super) fn event(&self, with_old_value: bool) -> ChangeEvent {
+        let mut event = self.event.clone();
+        if with_old_value {
+            event.old_value = self.old_object.as_ref().map(|o| o.value.clone());
+        }
+        event
+    }
+}
+
+/// The changes committed by a request, numbered from the current sequence
+/// number of the universe.
+pub(super) struct Changes {
+    pub(super) sequence: u64,
+    pub(super) changes: Vec<Change>,
+}
+
+impl Changes {
+    /// Records a change with the next sequence number, which is returned.
+    fn record(
+        &mut self,
+        collection: &CollectionDesc,
+        key: &[u8],
+        func: i32,
+        value: Option<Value>,
+        old_object: Option<Object>,
+    ) -> u64 {
+        self.sequence += 1;
+        let event = ChangeEvent {
+            collection: collection.name.clone(),
+            key: key.to_owned(),
+            func,
+            value,
+            old_value: None,
+            sequence: self.sequence,
+        };
+        self.changes.push(Change {
+            collection_id: collection.id,
+            event,
+            old_object,
+        });
+        self.sequence
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct Collection {
+    pub(super) desc: CollectionDesc,
+    objects: BTreeMap<Vec<u8>, Object>,
+    indexes: BTreeMap<String, Index>,
+    /// The statistics of the stored objects, including expired objects.
+    stats: Statistics,
+    /// The number of stored objects by deadline.
+    deadlines: BTreeMap<SystemTime, u64>,
+    create_time: SystemTime,
+    update_time: SystemTime,
+    pub(super) last_write_time: Option<SystemTime>,
+}
+
+impl Collection {
+    pub(super) fn new(desc: CollectionDesc) -> Self {
+        let indexes = desc
+            .options
+            .iter()
+            .flat_map(|o| &o.indexes)
+            .map(|(name, options)| (name.clone(), Index::new(options.clone())))
+            .collect();
+        let now = SystemTime::now();
+        Self {
+            desc,
+            objects: BTreeMap::new(),
+            indexes,
+            stats: Statistics::default(),
+            deadlines: BTreeMap::new(),
+            create_time: now,
+            update_time: now,
+            last_write_time: None,
+        }
+    }
+
+    /// Returns the descriptor of the collection with approximate statistics.
+    pub(super) fn desc(&self) -> CollectionDesc {
+        self.describe(false)
+    }
+
+    /// Returns the descriptor of the collection.
+    ///
+    /// Approximate statistics come from the statistics of the stored objects,
+    /// which include expired objects, while exact statistics scan the live
+    /// objects.
+    pub(super) fn describe(&self, exact: bool) -> CollectionDesc {
+        let now = SystemTime::now();
+        let (stats, num_objects, num_expired_objects) = if exact {
+            let mut stats = Statistics::default();
+            for (key, object) in self.objects.iter().filter(|(_, o)| !o.is_expired(now)) {
+                stats.add(key, &object.value);
+            }
+            let num_objects = stats.num_objects;
+            (stats, num_objects, self.stats.num_objects - num_objects)
+        } else {
+            let num_expired_objects: u64 = self.deadlines.range(..=now).map(|(_, n)| n).sum();
+            let num_objects = self.stats.num_objects.saturating_sub(num_expired_objects);
+            (self.stats.clone(), num_objects, num_expired_objects)
+        };
+        CollectionDesc {
+            properties: Some(CollectionProperties {
+                num_objects,
+                num_expired_objects,
+                indexes: self
+                    .indexes
+                    .iter()
+                    .map(|(name, index)| (name.clone(), index.properties()))
+                    .collect(),
+                logical_bytes: stats.key_bytes + stats.value_bytes,
+                physical_bytes: self.physical_bytes(),
+                average_value_bytes: match stats.num_objects {
+                    0 => 0,
+                    n => stats.value_bytes / n,
+                },
+                value_types: stats
+                    .value_types
+                    .iter()
+                    .map(
+                        |(&value_type, &(num_objects, value_bytes))| ValueTypeStatistics {
+                            value_type: value_type as i32,
+                            num_objects,
+                            value_bytes,
+                        },
+                    )
+                    .collect(),
+                create_time: Some(self.create_time.into()),
+                update_time: Some(self.update_time.into()),
+                last_write_time: self.last_write_time.map(Into::into),
+                exact,
+            }),
+            ..self.desc.clone()
+        }
+    }
+
+    /// Returns the approximate bytes to store the objects and the indexes.
+    pub(super) fn physical_bytes(&self) -> u64 {
+        let objects = self.stats.key_bytes
+            + self.stats.value_bytes
+            + self.stats.num_objects * OBJECT_OVERHEAD_BYTES;
+        objects + self.indexes.values().map(Index::num_bytes).sum::<u64>()
+    }
+
+    /// Replaces the options of the collection, building the indexes that are
+    /// added or changed and dropping the indexes that are removed.
+    pub(super) fn update_options(
+        &mut self,
+        options: Option<CollectionOptions>,
+    ) -> std::result::Result<(), Status> {
+        let mut indexes = BTreeMap::new();
+        for (name, index_options) in options.iter().flat_map(|o| &o.indexes) {
+            let index = match self.indexes.get(name) {
+                Some(index) if &index.options == index_options => index.clone(),
+                _ => self.build_index(name, index_options)?,
+            };
+            indexes.insert(name.clone(), index);
+        }
+        self.indexes = indexes;
+        self.desc.options = options;
+        self.update_time = SystemTime::now();
+        Ok(())
+    }
+
+    pub(super) fn create_index(
+        &mut self,
+        name: &str,
+        options: IndexOptions,
+    ) -> std::result::Result<IndexDesc, Status> {
+        if self.indexes.contains_key(name) {
+            return Err(Status::already_exists(format!(
+                "index {} already exists",
+                name
+            )));
+        }
+        let index = self.build_index(name, &options)?;
+        self.desc
+            .options
+            .get_or_insert_with(Default::default)
+            .indexes
+            .insert(name.to_owned(), options);
+        self.indexes.insert(name.to_owned(), index);
+        self.update_time = SystemTime::now();
+        Ok(self.index_desc(name, &self.indexes[name]))
+    }
+
+    pub(super) fn drop_index(&mut self, name: &str) -> std::result::Result<(), Status> {
+        self.indexes
+            .remove(name)
+            .ok_or_else(|| Status::not_found(format!("index {} not found", name)))?;
+        if let Some(options) = &mut self.desc.options {
+            options.indexes.remove(name);
+        }
+        self.update_time = SystemTime::now();
+        Ok(())
+    }
+
+    pub(super) fn index_descs(&self) -> Vec<IndexDesc> {
+        self.indexes
+            .iter()
+            .map(|(name, index)| self.index_desc(name, index))
+            .collect()
+    }
+
+    fn index_desc(&self, name: &str, index: &Index) -> IndexDesc {
+        IndexDesc {
+            name: name.to_owned(),
+            options: Some(index.options.clone()),
+            properties: Some(index.properties()),
+        }
+    }
+
+    /// Builds an index from the objects of the collection.
+    fn build_index(
+        &self,
+        name: &str,
+        options: &IndexOptions,
+    ) -> std::result::Result<Index, Status> {
+        let mut index = Index::new(options.clone());
+        for (key, object) in &self.objects {
+            index.insert(key, &object.value);
+        }
+        if options.unique {
+            if index.has_duplicates(|key| self.get(key).is_some()) {
+                return Err(Status::failed_precondition(format!(
+                    "unique index {} has duplicate values",
+                    name
+                )));
+            }
+        }
+        Ok(index)
+    }
+
+    /// Reverts a change committed to the collection.
+    pub(super) fn undo(&mut self, change: &Change) {
+        self.put(&change.event.key, change.old_object.clone());
+    }
+
+    /// Replaces or removes the object of the key, and updates the indexes
+    /// and the statistics.
+    fn put(&mut self, key: &[u8], object: Option<Object>) {
+        if let Some(object) = &object {
+            self.stats.add(key, &object.value);
+            if let Some(deadline) = object.deadline {
+                *self.deadlines.entry(deadline).or_default() += 1;
+            }
+        }
+        let old_object = match object {
+            Some(object) => self.objects.insert(key.to_owned(), object),
+            None => self.objects.remove(key),
+        };
+        if let Some(old_object) = &old_object {
+            self.stats.remove(key, &old_object.value);
+            if let Some(deadline) = old_object.deadline {
+                if let Some(n) = self.deadlines.get_mut(&deadline) {
+                    *n -= 1;
+                    if *n == 0 {
+                        self.deadlines.remove(&deadline);
+                    }
+                }
+            }
+        }
+        let object = self.objects.get(key);
+        for index in self.indexes.values_mut() {
+            if let Some(old_object) = &old_object {
+                index.remove(key, &old_object.value);
+            }
+            if let Some(object) = object {
+                index.insert(key, &object.value);
+            }
+        }
+    }
+
+    /// Returns the object of the key if it has not expired.
+    fn get(&self, key: &[u8]) -> Option<&Object> {
+        let now = SystemTime::now();
+        self.objects.get(key).filter(|o| !o.is_expired(now))
+    }
+
+    /// Evaluates an expression on the objects of the collection.
+    ///
+    /// Mutated objects take the sequence number of their change as their
+    /// version.
+    pub(super) fn execute(&mut self, expr: &ObjectExpr, changes: &mut Changes) -> ObjectResult {
+        let mut result = ObjectResult::default();
+        if let Err(err) = self.evaluate(expr, changes, &mut result) {
+            result.error = Some(err);
+        }
+        result
+    }
+
+    fn evaluate(
+        &mut self,
+        expr: &ObjectExpr,
+        changes: &mut Changes,
+        result: &mut ObjectResult,
+    ) -> Result<()> {
+        let (keys, limit, with_keys) = match &expr.target {
+            Some(object_expr::Target::Batch(batch)) => {
+                (batch.keys.clone(), 0, expr.filter.is_some())
+            }
+            Some(object_expr::Target::Range(range)) => (self.range_keys(range)?, range.limit, true),
+            Some(object_expr::Target::Index(index)) => (self.index_keys(index)?, index.limit, true),
+            None => return Err(invalid_argument("missing target")),
+        };
+        let mut aggregate = match (&expr.select, &expr.mutate) {
+            (Some(select), None) if expr.aggregate && select.with_version => {
+                return Err(invalid_argument("can not aggregate versions"));
+            }
+            (Some(select), None) if expr.aggregate => {
+                let func = SelectFunction::from_i32(select.func).ok_or_else(|| {
+                    invalid_argument(format!("unknown select function {}", select.func))
+                })?;
+                let operand = SelectExpr {
+                    index: select.index.clone(),
+                    ..Default::default()
+                };
+                Some((Aggregator::new(func)?, operand))
+            }
+            (_, Some(_)) if expr.aggregate => {
+                return Err(invalid_argument("can not aggregate mutations"));
+            }
+            _ => None,
+        };
+        let mut evaluated = 0;
+        for (index, key) in keys.into_iter().enumerate() {
+            let locate = |mut err: ObjectError| {
+                err.key = key.clone();
+                err.index = index as u64;
+                err
+            };
+            if let Some(filter) = &expr.filter {
+                if !self.filter(&key, filter).map_err(locate)? {
+                    continue;
+                }
+            }
+            if let Some((aggregator, operand)) = &mut aggregate {
+                let value = self.select(&key, operand).map_err(locate)?;
+                aggregator.push(value).map_err(locate)?;
+            } else {
+                let value = match (&expr.select, &expr.mutate) {
+                    (Some(select), None) => self.select(&key, select),
+                    (None, Some(mutate)) => self.mutate(&key, mutate, changes),
+                    _ => Err(invalid_argument("expect exactly one of select or mutate")),
+                }
+                .map_err(locate)?;
+                if matches!(&expr.select, Some(select) if select.with_version) {
+                    result
+                        .versions
+                        .push(self.get(&key).map_or(0, |o| o.version));
+                }
+                if with_keys {
+                    result.keys.push(key);
+                }
+                result.values.push(value);
+            }
+            evaluated += 1;
+            if evaluated == limit {
+                break;
+            }
+        }
+        if let Some((aggregator, _)) = aggregate {
+            result.values.push(aggregator.finish());
+        }
+        Ok(())
+    }
+
+    /// Evaluates the next chunk of a scan, with at most `limit` objects.
+    ///
+    /// The resume token of a chunk is the key of its last object.
+    pub(super) fn scan(&self, req: &ScanRequest, limit: u64) -> Result<ScanResponse> {
+        let mut range = req.range.clone().unwrap_or_default();
+        if !req.resume_token.is_empty() {
+            let bound = Some(RangeBound {
+                value: Some(range_bound::Value::BlobValue(req.resume_token.clone())),
+                included: false,
+            });
+            if range.reverse {
+                range.end = bound;
+            } else {
+                range.start = bound;
+            }
+        }
+        let select = req.select.clone().unwrap_or_default();
+        let mut resp = ScanResponse::default();
+        for key in self.range_iter(&range)? {
+            if resp.keys.len() as u64 == limit {
+                break;
+            }
+            let locate = |mut err: ObjectError| {
+                err.key = key.clone();
+                err
+            };
+            if let Some(filter) = &req.filter {
+                if !self.filter(key, filter).map_err(locate)? {
+                    continue;
+                }
+            }
+            resp.values.push(self.select(key, &select).map_err(locate)?);
+            resp.keys.push(key.clone());
+        }
+        resp.resume_token = match resp.keys.last() {
+            Some(key) => key.clone(),
+            None => req.resume_token.clone(),
+        };
+        Ok(resp)
+    }
+
+    /// Returns the keys of the live objects found through an index, in the
+    /// order of evaluation and without duplicates.
+    fn index_keys(&self, expr: &IndexExpr) -> Result<Vec<Vec<u8>>> {
+        let index = self.indexes.get(&expr.name).ok_or_else(|| {
+            eval::error(
+                ObjectErrorCode::NotFound,
+                format!("index {} not found", expr.name),
+            )
+        })?;
+        let mut keys: Vec<&Vec<u8>> = if expr.values.is_empty() {
+            index.range(expr.range.as_ref()).collect()
+        } else if expr.range.is_none() {
+            expr.values.iter().flat_map(|v| index.lookup(v)).collect()
+        } else {
+            return Err(invalid_argument("expect one of index values or range"));
+        };
+        if expr.reverse {
+            keys.reverse();
+        }
+        let mut seen = HashSet::new();
+        Ok(keys
+            .into_iter()
+            .filter(|key| self.get(key).is_some() && seen.insert(*key))
+            .cloned()
+            .collect())
+    }
+
+    fn range_keys(&self, range: &RangeExpr) -> Result<Vec<Vec<u8>>> {
+        Ok(self.range_iter(range)?.cloned().collect())
+    }
+
+    /// Iterates the keys of the live objects in the range, in its direction.
+    fn range_iter<'a>(
+        &'a self,
+        range: &'a RangeExpr,
+    ) -> Result<Box<dyn Iterator<Item = &'a Vec<u8>> + 'a>> {
+        let start = key_bound(&range.start)?;
+        let end = key_bound(&range.end)?;
+        // BTreeMap::range panics on empty ranges.
+        let empty = match (&start, &end) {
+            (Bound::Included(s), Bound::Included(e)) => s > e,
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+                s >= e
+            }
+            _ => false,
+        };
+        if empty {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let now = SystemTime::now();
+        let keys = self
+            .objects
+            .range((start, end))
+            .filter(move |(_, o)| !o.is_expired(now))
+            .map(|(key, _)| key);
+        Ok(if range.reverse {
+            Box::new(keys.rev())
+        } else {
+            Box::new(keys)
+        })
+    }
+
+    fn select(&self, key: &[u8], expr: &SelectExpr) -> Result<Value> {
+        let object = self.get(key);
+        match SelectFunction::from_i32(expr.func) {
+            Some(SelectFunction::Version) => Ok(object.map_or(0, |o| o.version as i64).into()),
+            Some(SelectFunction::Ttl) => {
+                let deadline = object.and_then(|o| o.deadline);
+                Ok(deadline.map_or_else(Value::default, |deadline| {
+                    let ttl = deadline
+                        .duration_since(SystemTime::now())
+                        .unwrap_or_default();
+                    ttl.into()
+                }))
+            }
+            _ => apply_select(&object.map(|o| o.value.clone()), expr),
+        }
+    }
+
+    fn filter(&self, key: &[u8], filter: &FilterExpr) -> Result<bool> {
+        eval_filter_with(filter, &|select| self.select(key, select))
+    }
+
+    fn mutate(&mut self, key: &[u8], expr: &MutateExpr, changes: &mut Changes) -> Result<Value> {
+        self.check_key(key)?;
+        if expr.expected_version != 0 {
+            let version = self.get(key).map_or(0, |o| o.version);
+            if version != expr.expected_version {
+                return Err(eval::error(
+                    ObjectErrorCode::VersionMismatch,
+                    format!("expect version {}, got {}", expr.expected_version, version),
+                ));
+            }
+        }
+        if let Some(condition) = &expr.condition {
+            if !self.filter(key, condition)? {
+                return Err(eval::error(
+                    ObjectErrorCode::ConditionFailed,
+                    "condition not satisfied",
+                ));
+            }
+        }
+        let now = SystemTime::now();
+        match MutateFunction::from_i32(expr.func) {
+            Some(func @ (MutateFunction::Expire | MutateFunction::Persist)) => {
+                let deadline = match (func, expr.args.as_slice()) {
+                    (MutateFunction::Expire, [deadline]) => Some(deadline_of(deadline, now)?),
+                    (MutateFunction::Persist, []) => None,
+                    _ => {
+                        return Err(invalid_argument(format!(
+                            "wrong number of arguments for {:?}",
+                            func
+                        )))
+                    }
+                };
+                let old_object = match self.objects.get(key) {
+                    Some(object) if !object.is_expired(now) => object.clone(),
+                    _ => return Ok(false.into()),
+                };
+                // PERSIST on an object that does not expire changes nothing.
+                if deadline.is_none() && old_object.deadline.is_none() {
+                    return Ok(false.into());
+                }
+                let version = changes.record(
+                    &self.desc,
+                    key,
+                    expr.func,
+                    Some(old_object.value.clone()),
+                    Some(old_object.clone()),
+                );
+                let object = Object {
+                    deadline,
+                    version,
+                    ..old_object
+                };
+                self.put(key, Some(object));
+                self.last_write_time = Some(now);
+                return Ok(true.into());
+            }
+            _ => {}
+        }
+
+        // Expired objects are collected once a mutation targets them.
+        let old_object = self
+            .objects
+            .get(key)
+            .filter(|o| !o.is_expired(now))
+            .cloned();
+        let deadline = match &old_object {
+            Some(o) => o.deadline,
+            None => self.default_deadline(now),
+        };
+        let mut value = old_object.as_ref().map(|o| o.value.clone());
+        let output = apply_mutate(&mut value, expr)?;
+        match value {
+            Some(value) => {
+                self.check_value(&value)?;
+                self.check_unique(key, &value)?;
+                let version =
+                    changes.record(&self.desc, key, expr.func, Some(value.clone()), old_object);
+                let object = Object {
+                    value,
+                    version,
+                    deadline,
+                };
+                self.put(key, Some(object));
+                self.last_write_time = Some(now);
+            }
+            None => {
+                if old_object.is_some() {
+                    changes.record(&self.desc, key, expr.func, None, old_object);
+                    self.last_write_time = Some(now);
+                }
+                self.put(key, None);
+            }
+        }
+        Ok(output)
+    }
+
+    fn check_key(&self, key: &[u8]) -> Result<()> {
+        let key_type = self.desc.options.as_ref().map(|o| o.key_type());
+        let valid = match key_type {
+            Some(ValueType::Text) => std::str::from_utf8(key).is_ok(),
+            Some(ValueType::I64) => i64_from_key(key).is_some(),
+            Some(ValueType::Timestamp) => timestamp_from_key(key).is_some(),
+            _ => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(eval::error(
+                ObjectErrorCode::TypeMismatch,
+                format!("expect {:?} keys", key_type.unwrap_or_default()),
+            ))
+        }
+    }
+
+    /// Checks the value against the value schema of the collection before it
+    /// is stored.
+    fn check_value(&self, value: &Value) -> Result<()> {
+        let schema = self
+            .desc
+            .options
+            .as_ref()
+            .and_then(|o| o.value_schema.as_ref());
+        match schema {
+            Some(schema) => schema
+                .check(value)
+                .map_err(|err| eval::error(ObjectErrorCode::TypeMismatch, err.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that no other object has the same value in a unique index.
+    fn check_unique(&self, key: &[u8], value: &Value) -> Result<()> {
+        for (name, index) in self.indexes.iter().filter(|(_, i)| i.options.unique) {
+            for v in index.values_of(value) {
+                if index
+                    .lookup(&v)
+                    .any(|other| other != key && self.get(other).is_some())
+                {
+                    return Err(eval::error(
+                        ObjectErrorCode::AlreadyExists,
+                        format!("another object has the same value in unique index {}", name),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn default_deadline(&self, now: SystemTime) -> Option<SystemTime> {
+        let ttl = self.desc.options.as_ref()?.default_ttl.clone()?;
+        time::Duration::try_from(ttl).ok().map(|ttl| now + ttl)
+    }
+}
+
+/// Converts a range bound to a key bound. Keys are compared as bytes, so i64
+/// and timestamp bounds are encoded like i64 and timestamp keys.
+fn key_bound(bound: &Option<RangeBound>) -> Result<Bound<Vec<u8>>> {
+    let bound = match bound {
+        Some(bound) => bound,
+        None => return Ok(Bound::Unbounded),
+    };
+    let key = match &bound.value {
+        None => return Ok(Bound::Unbounded),
+        Some(range_bound::Value::BlobValue(v)) => v.clone(),
+        Some(range_bound::Value::TextValue(v)) => v.as_bytes().to_vec(),
+        Some(range_bound::Value::I64Value(v)) => i64_key(*v),
+        Some(range_bound::Value::TimestampValue(v)) => match timestamp_key(v) {
+            Some(key) => key,
+            None => return Err(invalid_argument("timestamp key bound out of range")),
+        },
+        Some(_) => {
+            return Err(invalid_argument(
+                "expect blob, text, i64 or timestamp key bounds",
+            ))
+        }
+    };
+    Ok(if bound.included {
+        Bound::Included(key)
+    } else {
+        Bound::Excluded(key)
+    })
+}
+
+/// Returns whether the key is in the range of keys.
+pub(super) fn range_contains(range: &RangeExpr, key: &[u8]) -> Result<bool> {
+    let start = key_bound(&range.start)?;
+    let end = key_bound(&range.end)?;
+    let bounds = (
+        start.as_ref().map(Vec::as_slice),
+        end.as_ref().map(Vec::as_slice),
+    );
+    Ok(RangeBounds::<[u8]>::contains(&bounds, key))
+}
+
+/// Returns the deadline of a Duration from now or an absolute Timestamp.
+fn deadline_of(v: &Value, now: SystemTime) -> Result<SystemTime> {
+    match &v.value {
+        Some(value::Value::DurationValue(ttl)) => {
+            // Durations that are not positive expire the object immediately.
+            Ok(time::Duration::try_from(ttl.clone()).map_or(now, |ttl| now + ttl))
+        }
+        Some(value::Value::TimestampValue(deadline)) => {
+            SystemTime::try_from(deadline.clone()).map_err(|err| invalid_argument(err.to_string()))
+        }
+        _ => Err(invalid_argument("EXPIRE expects a duration or timestamp")),
+    }
+}
+
+/// Checks that the default TTL of the options is not negative, and that the
+/// key type and the value schema are supported.
+pub(super) fn check_options(
+    options: &Option<CollectionOptions>,
+) -> std::result::Result<(), Status> {
+    let options = match options {
+        Some(options) => options,
+        None => return Ok(()),
+    };
+    if let Some(ttl) = &options.default_ttl {
+        if time::Duration::try_from(ttl.clone()).is_err() {
+            return Err(Status::invalid_argument("default_ttl must not be negative"));
+        }
+    }
+    match ValueType::from_i32(options.key_type) {
+        Some(
+            ValueType::Unspecified
+            | ValueType::Blob
+            | ValueType::Text
+            | ValueType::I64
+            | ValueType::Timestamp,
+        ) => {}
+        _ => {
+            return Err(Status::invalid_argument(
+                "key_type must be blob, text, i64 or timestamp",
+            ))
+        }
+    }
+    if options.indexes.keys().any(|name| name.is_empty()) {
+        return Err(Status::invalid_argument("index names must not be empty"));
+    }
+    match &options.value_schema {
+        Some(schema) => check_schema(schema),
+        None => Ok(()),
+    }
+}
+
+fn check_schema(schema: &ValueSchema) -> std::result::Result<(), Status> {
+    let value_type = ValueType::from_i32(schema.value_type)
+        .ok_or_else(|| Status::invalid_argument("unknown value type in value_schema"))?;
+    let has_element = matches!(
+        value_type,
+        ValueType::List | ValueType::Set | ValueType::SortedSet | ValueType::Map
+    );
+    if schema.element.is_some() && !has_element {
+        return Err(Status::invalid_argument(format!(
+            "{:?} schema must not have an element",
+            value_type
+        )));
+    }
+    if (schema.key.is_some() || !schema.fields.is_empty()) && value_type != ValueType::Map {
+        return Err(Status::invalid_argument(format!(
+            "{:?} schema must not have a key or fields",
+            value_type
+        )));
+    }
+    for child in [&schema.element, &schema.key].into_iter().flatten() {
+        check_schema(child)?;
+    }
+    schema.fields.values().try_for_each(check_schema)
+}
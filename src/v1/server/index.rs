@@ -0,0 +1,198 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use prost::Message;
+
+use crate::v1::{
+    eval::{apply_select, compare},
+    *,
+};
+
+/// A secondary index of a collection.
+///
+/// Entries are kept for expired objects until the objects are collected, so
+/// lookups must skip the keys of expired objects.
+#[derive(Clone)]
+pub(super) struct Index {
+    pub(super) options: IndexOptions,
+    /// The indexed values and the keys of their objects, sorted by value and
+    /// then by key.
+    entries: Vec<(Value, Vec<u8>)>,
+    /// The bytes of the values and keys of the entries.
+    num_bytes: u64,
+}
+
+impl Index {
+    pub(super) fn new(options: IndexOptions) -> Self {
+        Self {
+            options,
+            entries: Vec::new(),
+            num_bytes: 0,
+        }
+    }
+
+    pub(super) fn properties(&self) -> IndexProperties {
+        IndexProperties {
+            state: IndexState::Ready as i32,
+            num_entries: self.entries.len() as u64,
+        }
+    }
+
+    pub(super) fn num_bytes(&self) -> u64 {
+        self.num_bytes
+    }
+
+    /// Returns the values that index an object value.
+    pub(super) fn values_of(&self, value: &Value) -> Vec<Value> {
+        let value = if self.options.field.is_empty() {
+            value.clone()
+        } else {
+            let select = SelectExpr {
+                index: Some(self.options.field.clone().into()),
+                ..Default::default()
+            };
+            match apply_select(&Some(value.clone()), &select) {
+                Ok(value) => value,
+                Err(_) => return Vec::new(),
+            }
+        };
+        let values = if self.options.members {
+            let members = match value.value {
+                Some(value::Value::SetValue(set)) => set.keys,
+                Some(value::Value::SortedSetValue(set)) => set.members,
+                _ => None,
+            };
+            members
+                .and_then(|members| members.into_values().ok())
+                .unwrap_or_default()
+        } else {
+            vec![value]
+        };
+        values.into_iter().filter(indexable).collect()
+    }
+
+    pub(super) fn insert(&mut self, key: &[u8], value: &Value) {
+        for v in self.values_of(value) {
+            let pos = self
+                .entries
+                .partition_point(|entry| entry_order(entry, &v, key) == Ordering::Less);
+            self.num_bytes += (v.encoded_len() + key.len()) as u64;
+            self.entries.insert(pos, (v, key.to_owned()));
+        }
+    }
+
+    pub(super) fn remove(&mut self, key: &[u8], value: &Value) {
+        for v in self.values_of(value) {
+            if let Ok(pos) = self
+                .entries
+                .binary_search_by(|entry| entry_order(entry, &v, key))
+            {
+                self.num_bytes -= (v.encoded_len() + key.len()) as u64;
+                self.entries.remove(pos);
+            }
+        }
+    }
+
+    /// Returns the keys indexed by values equal to `v`, in key order.
+    pub(super) fn lookup<'a>(&'a self, v: &'a Value) -> impl Iterator<Item = &'a Vec<u8>> + 'a {
+        let start = if indexable(v) {
+            self.entries
+                .partition_point(|(e, _)| value_order(e, v) == Ordering::Less)
+        } else {
+            self.entries.len()
+        };
+        self.entries[start..]
+            .iter()
+            .take_while(move |(e, _)| value_order(e, v) == Ordering::Equal)
+            .map(|(_, key)| key)
+    }
+
+    /// Returns the keys indexed by values in the range, or all the indexed
+    /// keys if there is no range.
+    pub(super) fn range<'a>(
+        &'a self,
+        range: Option<&'a RangeValue>,
+    ) -> impl Iterator<Item = &'a Vec<u8>> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(v, _)| range.map_or(true, |range| range_contains(range, v)))
+            .map(|(_, key)| key)
+    }
+
+    /// Returns whether a value indexes more than one of the objects for which
+    /// `live` is true.
+    pub(super) fn has_duplicates(&self, live: impl Fn(&[u8]) -> bool) -> bool {
+        let mut last: Option<&Value> = None;
+        for (v, _) in self.entries.iter().filter(|(_, key)| live(key)) {
+            if matches!(last, Some(last) if value_order(last, v) == Ordering::Equal) {
+                return true;
+            }
+            last = Some(v);
+        }
+        false
+    }
+}
+
+/// Orders indexed values by type, and then by value within a type. Integers
+/// and floats are ordered together.
+fn rank(v: &Value) -> Option<u8> {
+    match &v.value {
+        Some(value::Value::I64Value(_) | value::Value::F64Value(_)) => Some(0),
+        Some(value::Value::BlobValue(_)) => Some(1),
+        Some(value::Value::TextValue(_)) => Some(2),
+        Some(value::Value::TimestampValue(_)) => Some(3),
+        Some(value::Value::DurationValue(_)) => Some(4),
+        Some(value::Value::DecimalValue(_)) => Some(5),
+        _ => None,
+    }
+}
+
+/// Returns whether a value can be indexed. NaNs and invalid decimals are not
+/// ordered, even with themselves, so they are not indexed.
+fn indexable(v: &Value) -> bool {
+    rank(v).is_some() && compare(v, v) == Some(Ordering::Equal)
+}
+
+/// Orders indexable values, which are always comparable within a rank.
+fn value_order(a: &Value, b: &Value) -> Ordering {
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| compare(a, b).unwrap_or(Ordering::Equal))
+}
+
+fn entry_order(entry: &(Value, Vec<u8>), v: &Value, key: &[u8]) -> Ordering {
+    value_order(&entry.0, v).then_with(|| entry.1.as_slice().cmp(key))
+}
+
+/// Returns whether the value is in the range. Values that are not comparable
+/// with a bound are not in the range.
+fn range_contains(range: &RangeValue, v: &Value) -> bool {
+    let check = |bound: &Option<RangeBound>, inside: Ordering| {
+        let (bound, included) = match bound {
+            Some(RangeBound {
+                value: Some(bound),
+                included,
+            }) => (Value::from(bound.clone()), *included),
+            _ => return true,
+        };
+        match compare(v, &bound) {
+            Some(Ordering::Equal) => included,
+            Some(ord) => ord == inside,
+            None => false,
+        }
+    };
+    check(&range.start, Ordering::Greater) && check(&range.end, Ordering::Less)
+}
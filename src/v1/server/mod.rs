@@ -0,0 +1,226 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An in-memory implementation of the Engula service.
+//!
+//! [`MemoryServer`] keeps all databases and collections in memory and
+//! evaluates every expression defined by the APIs, so that tests can run
+//! against a real tonic client without a cluster.
+//!
+//! Expired objects are hidden from reads right away, but they are only
+//! collected when a mutation targets them.
+//! Each database retains its latest 10,000 changes for watches to resume
+//! from.
+//!
+//! ```no_run
+//! use engula_apis::v1::{engula_client::EngulaClient, server::MemoryServer};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let addr = MemoryServer::new().spawn().await?;
+//! let client = EngulaClient::connect(format!("http://{}", addr)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod collection;
+mod index;
+#[cfg(test)]
+mod tests;
+mod universe;
+
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, Notify},
+};
+use tokio_stream::{
+    wrappers::{ReceiverStream, TcpListenerStream},
+    Stream,
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+use self::universe::Universe;
+use crate::v1::{
+    engula_server::{Engula, EngulaServer},
+    BatchRequest, BatchResponse, ScanRequest, ScanResponse, WatchRequest, WatchResponse,
+};
+
+const DEFAULT_SCAN_BATCH_SIZE: u64 = 100;
+const WATCH_CHANNEL_SIZE: usize = 16;
+
+/// An in-memory Engula server.
+///
+/// Clones of the server share the same universe. Each batch and each chunk of
+/// a scan is handled under a single lock, so requests are applied one batch
+/// at a time.
+#[derive(Clone, Default)]
+pub struct MemoryServer {
+    universe: Arc<Mutex<Universe>>,
+    /// Wakes up watches after each batch.
+    changed: Arc<Notify>,
+}
+
+impl MemoryServer {
+    /// Creates a server with an empty universe.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a tonic service that can be added to a router.
+    pub fn into_service(self) -> EngulaServer<Self> {
+        EngulaServer::new(self)
+    }
+
+    /// Serves connections from the listener until an error occurs.
+    pub async fn serve(self, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(self.into_service())
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+    }
+
+    /// Spawns the server on a random local port and returns its address.
+    ///
+    /// This must be called within a tokio runtime.
+    pub async fn spawn(self) -> std::io::Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(self.serve(listener));
+        Ok(addr)
+    }
+}
+
+/// Locks the universe. A request that panics while holding the lock only
+/// fails itself, so later requests recover the lock from poisoning.
+fn lock(universe: &Mutex<Universe>) -> MutexGuard<'_, Universe> {
+    universe.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[tonic::async_trait]
+impl Engula for MemoryServer {
+    async fn batch(&self, req: Request<BatchRequest>) -> Result<Response<BatchResponse>, Status> {
+        let resp = lock(&self.universe).batch(req.into_inner());
+        self.changed.notify_waiters();
+        resp.map(Response::new)
+    }
+
+    type ScanStream = Pin<Box<dyn Stream<Item = Result<ScanResponse, Status>> + Send>>;
+
+    async fn scan(&self, req: Request<ScanRequest>) -> Result<Response<Self::ScanStream>, Status> {
+        let mut req = req.into_inner();
+        // Later chunks address the collection by id, so that the scan
+        // continues if the collection is renamed or moved.
+        req.id = lock(&self.universe).scan_collection(&req)?.desc.id;
+        let remaining = req
+            .range
+            .as_ref()
+            .map(|range| range.limit)
+            .filter(|&limit| limit > 0);
+        let chunks = ScanChunks {
+            universe: self.universe.clone(),
+            req,
+            remaining,
+            done: false,
+        };
+        Ok(Response::new(Box::pin(tokio_stream::iter(chunks))))
+    }
+
+    type WatchStream = ReceiverStream<Result<WatchResponse, Status>>;
+
+    async fn watch(
+        &self,
+        req: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let mut req = req.into_inner();
+        let mut after = lock(&self.universe).start_watch(&mut req)?;
+        let universe = self.universe.clone();
+        let changed = self.changed.clone();
+        let (tx, rx) = mpsc::channel(WATCH_CHANNEL_SIZE);
+        tokio::spawn(async move {
+            loop {
+                // A notification wakes up the futures created before it, so
+                // no batch is missed between the watch and the wait.
+                let notified = changed.notified();
+                let watched = lock(&universe).watch(&req, after);
+                let events = match watched {
+                    Ok((events, next)) => {
+                        after = next;
+                        events
+                    }
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        return;
+                    }
+                };
+                if !events.is_empty() {
+                    if tx.send(Ok(WatchResponse { events })).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                tokio::select! {
+                    _ = notified => {}
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Produces the chunks of a scan, locking the universe for each chunk.
+struct ScanChunks {
+    universe: Arc<Mutex<Universe>>,
+    req: ScanRequest,
+    remaining: Option<u64>,
+    done: bool,
+}
+
+impl Iterator for ScanChunks {
+    type Item = Result<ScanResponse, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let batch_size = match self.req.batch_size {
+            0 => DEFAULT_SCAN_BATCH_SIZE,
+            n => n,
+        };
+        let limit = self.remaining.map_or(batch_size, |n| n.min(batch_size));
+        let chunk = lock(&self.universe).scan(&self.req, limit);
+        let resp = match chunk {
+            Ok(resp) => resp,
+            Err(status) => {
+                self.done = true;
+                return Some(Err(status));
+            }
+        };
+        let n = resp.keys.len() as u64;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= n;
+        }
+        self.done = n < limit || self.remaining == Some(0);
+        if n == 0 {
+            return None;
+        }
+        self.req.resume_token = resp.resume_token.clone();
+        Some(Ok(resp))
+    }
+}
@@ -0,0 +1,525 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tokio_stream::StreamExt;
+use tonic::{transport::Channel, Code};
+
+use super::MemoryServer;
+use crate::v1::{engula_client::EngulaClient, universe_request::Request as R, *};
+
+async fn connect() -> EngulaClient<Channel> {
+    connect_to(MemoryServer::new()).await
+}
+
+async fn connect_to(server: MemoryServer) -> EngulaClient<Channel> {
+    let addr = server.spawn().await.unwrap();
+    EngulaClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+async fn setup(client: &mut EngulaClient<Channel>, dbname: &str, collections: &[&str]) {
+    let mut batch = Batch::new().universe(R::CreateDatabase(CreateDatabaseRequest {
+        name: dbname.to_owned(),
+        options: None,
+    }));
+    for name in collections {
+        batch = batch.universe(R::CreateCollection(CreateCollectionRequest {
+            name: (*name).to_owned(),
+            dbname: dbname.to_owned(),
+            ..Default::default()
+        }));
+    }
+    client.batch(batch.build()).await.unwrap();
+}
+
+#[tokio::test]
+async fn batch() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["users"]).await;
+
+    let batch = Batch::new()
+        .db("app")
+        .collection("users")
+        .set("alice", 1)
+        .add("alice", 2)
+        .get("alice")
+        .get("bob");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert_eq!(result.take::<i64>(1).unwrap(), 3);
+    assert_eq!(result.take::<i64>(2).unwrap(), 3);
+    assert_eq!(result.take::<Option<i64>>(3).unwrap(), None);
+
+    let batch = Batch::new().db("app").collection("users").add("alice", "x");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    assert!(shape.decode(resp).unwrap().take::<i64>(0).is_err());
+
+    // A failed database request applies nothing and fails on its own.
+    setup(&mut client, "other", &["b"]).await;
+    let batch = Batch::new()
+        .db("app")
+        .collection("users")
+        .set("bob", 1)
+        .collection("nope")
+        .get("alice")
+        .db("other")
+        .collection("b")
+        .add("x", 5);
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    for i in 0..2 {
+        let err = result.take_result(i).unwrap().error.unwrap();
+        assert_eq!(err.code(), ObjectErrorCode::NotFound);
+    }
+    assert_eq!(result.take::<i64>(2).unwrap(), 5);
+
+    let batch = Batch::new().db("app").collection("users").get("bob");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert_eq!(result.take::<Option<i64>>(0).unwrap(), None);
+}
+
+#[tokio::test]
+async fn scan() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["numbers"]).await;
+    let mut batch = Batch::new().db("app").collection("numbers");
+    for i in 0..25i64 {
+        batch = batch.set(format!("k{:02}", i), i);
+    }
+    client.batch(batch.build()).await.unwrap();
+
+    let mut stream = client
+        .scan(ScanRequest::new("app", "numbers").with_batch_size(10))
+        .await
+        .unwrap()
+        .into_inner();
+    let mut chunks = Vec::new();
+    while let Some(resp) = stream.next().await {
+        chunks.push(resp.unwrap().keys.len());
+    }
+    assert_eq!(chunks, [10, 10, 5]);
+
+    let req = ScanRequest::new("app", "numbers")
+        .with_range(RangeExpr::from_bounds("k05".to_owned()..="k20".to_owned()).with_limit(4))
+        .with_filter(filter::value().gt(6))
+        .with_batch_size(3);
+    let mut stream = client.scan_as::<i64>(req.clone()).await.unwrap();
+    let mut values = Vec::new();
+    while let Some(item) = stream.next().await {
+        values.push(item.unwrap().1);
+    }
+    assert_eq!(values, [7, 8, 9, 10]);
+
+    let mut stream = client
+        .scan_as::<i64>(req.with_resume_token("k10"))
+        .await
+        .unwrap();
+    let mut values = Vec::new();
+    while let Some(item) = stream.next().await {
+        values.push(item.unwrap().1);
+    }
+    assert_eq!(values, [11, 12, 13, 14]);
+}
+
+#[tokio::test]
+async fn watch() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["a", "b"]).await;
+
+    let mut all = client
+        .watch(WatchRequest::new("app").with_old_value(true))
+        .await
+        .unwrap()
+        .into_inner();
+    let mut ranged = client
+        .watch(
+            WatchRequest::new("app")
+                .with_collection("a")
+                .with_range(RangeExpr::from_bounds("k".to_owned()..)),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+    let batch = Batch::new()
+        .db("app")
+        .collection("a")
+        .set("k1", 1)
+        .set("a1", 2)
+        .collection("b")
+        .set("k2", 3);
+    client.batch(batch.build()).await.unwrap();
+    let batch = Batch::new().db("app").collection("a").add("k1", 5);
+    client.batch(batch.build()).await.unwrap();
+
+    let mut events = Vec::new();
+    while events.len() < 4 {
+        events.extend(all.next().await.unwrap().unwrap().events);
+    }
+    let keys: Vec<_> = events.iter().map(|e| e.key.as_slice()).collect();
+    assert_eq!(keys, [b"k1", b"a1", b"k2", b"k1"]);
+    assert_eq!(events[3].old_value, Some(1.into()));
+    assert_eq!(events[3].value, Some(6.into()));
+
+    let mut events = Vec::new();
+    while events.len() < 2 {
+        events.extend(ranged.next().await.unwrap().unwrap().events);
+    }
+    assert!(events.iter().all(|e| e.collection == "a" && e.key == b"k1"));
+    assert_eq!(events[1].old_value, None);
+
+    let status = client.watch(WatchRequest::new("nope")).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn atomic_batch_rolls_back() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["a"]).await;
+    setup(&mut client, "other", &["b"]).await;
+    client
+        .batch(Batch::new().db("app").collection("a").set("x", 1).build())
+        .await
+        .unwrap();
+
+    // A failed mutation.
+    let batch = Batch::new()
+        .db("app")
+        .collection("a")
+        .set("y", 1)
+        .add("x", 1)
+        .mutate("x", MutateExpr::set_if_absent(0))
+        .atomic();
+    let status = client.batch(batch.build()).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert!(status.message().contains("ConditionFailed"));
+
+    // Failed conditions are not conflicts, so transactions are not retried.
+    let mut attempts = 0;
+    let status = client
+        .transact(3, |_| {
+            attempts += 1;
+            let batch = Batch::new()
+                .db("app")
+                .collection("a")
+                .mutate("x", MutateExpr::set_if_absent(0));
+            async move { Ok(batch.build()) }
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert_eq!(attempts, 1);
+
+    // A failed database request after a successful one.
+    let batch = Batch::new()
+        .db("app")
+        .collection("a")
+        .set("y", 1)
+        .db("other")
+        .collection("nope")
+        .set("z", 1)
+        .atomic();
+    assert!(client.batch(batch.build()).await.is_err());
+
+    let batch = Batch::new().db("app").collection("a").get("x").get("y");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert_eq!(result.take::<i64>(0).unwrap(), 1);
+    assert_eq!(result.take::<Option<i64>>(1).unwrap(), None);
+}
+
+#[tokio::test]
+async fn snapshot_reads() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["a"]).await;
+
+    let batch = Batch::new().db("app").collection("a").set("x", 1);
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let sequence = shape.decode(resp).unwrap().sequence();
+    client
+        .batch(Batch::new().db("app").collection("a").set("x", 2).build())
+        .await
+        .unwrap();
+
+    let read = |options: ReadOptions| {
+        Batch::new()
+            .db("app")
+            .read_options(options)
+            .collection("a")
+            .get("x")
+    };
+    let batch = read(ReadOptions::snapshot(sequence));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert_eq!(result.sequence(), sequence);
+    assert_eq!(result.take::<i64>(0).unwrap(), 1);
+
+    let batch = read(ReadOptions::strong());
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    assert_eq!(shape.decode(resp).unwrap().take::<i64>(0).unwrap(), 2);
+
+    let batch = read(ReadOptions::snapshot(sequence + 10));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let err = shape.decode(resp).unwrap().take_result(0).unwrap().error;
+    assert_eq!(err.unwrap().code(), ObjectErrorCode::InvalidArgument);
+
+    let batch = Batch::new()
+        .db("app")
+        .read_options(ReadOptions::snapshot(sequence))
+        .collection("a")
+        .set("x", 3);
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let err = shape.decode(resp).unwrap().take_result(0).unwrap().error;
+    assert_eq!(err.unwrap().code(), ObjectErrorCode::InvalidArgument);
+}
+
+#[tokio::test]
+async fn index_lookups() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &[]).await;
+    let options = CollectionOptions::default()
+        .with_index("by_email", IndexOptions::field("email").with_unique(true))
+        .with_index("by_age", IndexOptions::field("age"));
+    let batch = Batch::new().universe(R::CreateCollection(CreateCollectionRequest {
+        name: "users".to_owned(),
+        dbname: "app".to_owned(),
+        options: Some(options),
+        ..Default::default()
+    }));
+    client.batch(batch.build()).await.unwrap();
+
+    let user = |email: &str, age: i64| {
+        Value::from(MapValue::from([
+            ("email".to_owned(), Value::from(email)),
+            ("age".to_owned(), age.into()),
+        ]))
+    };
+    let batch = Batch::new()
+        .db("app")
+        .collection("users")
+        .set("u1", user("a@x", 30))
+        .set("u2", user("b@x", 20))
+        .set("u3", user("c@x", 40))
+        .set("u4", user("a@x", 50));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    let err = result.take_result(3).unwrap().error.unwrap();
+    assert_eq!(err.code(), ObjectErrorCode::AlreadyExists);
+
+    let age = SelectExpr::get().with_index("age");
+    let batch = Batch::new()
+        .db("app")
+        .collection("users")
+        .expr(
+            ObjectExpr::index(IndexExpr::lookup("by_email", ["a@x", "d@x"]))
+                .with_select(age.clone()),
+        )
+        .expr(ObjectExpr::index(IndexExpr::range("by_age", 25..)).with_select(age))
+        .expr(ObjectExpr::index(IndexExpr::all("nope")));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    let lookup = result.take_result(0).unwrap();
+    assert_eq!(lookup.keys, [b"u1"]);
+    assert_eq!(result.take_all::<i64>(1).unwrap(), [30, 40]);
+    let err = result.take_result(2).unwrap().error.unwrap();
+    assert_eq!(err.code(), ObjectErrorCode::NotFound);
+}
+
+#[tokio::test]
+async fn addressing_by_id() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["a"]).await;
+    let batch = Batch::new().universe(R::DescribeCollection(DescribeCollectionRequest {
+        name: "a".to_owned(),
+        dbname: "app".to_owned(),
+        ..Default::default()
+    }));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let id = match shape.decode(resp).unwrap().take_universes().pop() {
+        Some(UniverseResponse {
+            response: Some(universe_response::Response::DescribeCollection(resp)),
+        }) => resp.desc.unwrap().id,
+        other => panic!("unexpected response {:?}", other),
+    };
+
+    let batch = Batch::new()
+        .universe(R::CreateIndex(CreateIndexRequest {
+            name: "by_value".to_owned(),
+            options: Some(IndexOptions::value()),
+            coid: id,
+            ..Default::default()
+        }))
+        .universe(R::ListIndexes(ListIndexesRequest {
+            coid: id,
+            ..Default::default()
+        }));
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    match shape.decode(resp).unwrap().take_universes().pop() {
+        Some(UniverseResponse {
+            response: Some(universe_response::Response::ListIndexes(resp)),
+        }) => assert_eq!(resp.descs.len(), 1),
+        other => panic!("unexpected response {:?}", other),
+    }
+
+    // The watch follows the collection when it is renamed.
+    let req = WatchRequest {
+        id,
+        ..Default::default()
+    };
+    let mut stream = client.watch(req).await.unwrap().into_inner();
+    let batch = Batch::new().universe(R::RenameCollection(RenameCollectionRequest {
+        new_name: "b".to_owned(),
+        id,
+        ..Default::default()
+    }));
+    client.batch(batch.build()).await.unwrap();
+    client
+        .batch(Batch::new().db("app").collection("b").set("x", 1).build())
+        .await
+        .unwrap();
+    let events = stream.next().await.unwrap().unwrap().events;
+    assert_eq!(events[0].collection, "b");
+    assert_eq!(events[0].key, b"x");
+}
+
+#[tokio::test]
+async fn i64_key_ranges() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &[]).await;
+    let batch = Batch::new().universe(R::CreateCollection(CreateCollectionRequest {
+        name: "numbers".to_owned(),
+        dbname: "app".to_owned(),
+        options: Some(CollectionOptions::default().with_key_type(ValueType::I64)),
+        ..Default::default()
+    }));
+    client.batch(batch.build()).await.unwrap();
+    let mut batch = Batch::new().db("app").collection("numbers");
+    for i in -3..3 {
+        batch = batch.set(i64_key(i), i);
+    }
+    client.batch(batch.build()).await.unwrap();
+
+    let req = ScanRequest::new("app", "numbers").with_range(RangeExpr::from_bounds(-2i64..1));
+    let mut stream = client.scan_as::<i64>(req).await.unwrap();
+    let mut values = Vec::new();
+    while let Some(item) = stream.next().await {
+        let (key, value) = item.unwrap();
+        assert_eq!(i64_from_key(&key), Some(value));
+        values.push(value);
+    }
+    assert_eq!(values, [-2, -1, 0]);
+}
+
+#[tokio::test]
+async fn index_skips_unordered_values() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &[]).await;
+    let options = CollectionOptions::default().with_index("by_value", IndexOptions::value());
+    let batch = Batch::new().universe(R::CreateCollection(CreateCollectionRequest {
+        name: "numbers".to_owned(),
+        dbname: "app".to_owned(),
+        options: Some(options),
+        ..Default::default()
+    }));
+    client.batch(batch.build()).await.unwrap();
+
+    let invalid = Decimal {
+        value: "x".to_owned(),
+    };
+    let batch = Batch::new()
+        .db("app")
+        .collection("numbers")
+        .set("a", 2.0)
+        .set("b", f64::NAN)
+        .set("c", 1)
+        .set("d", f64::NAN)
+        .set("e", 3.5)
+        .set("f", invalid);
+    client.batch(batch.build()).await.unwrap();
+
+    let batch = Batch::new()
+        .db("app")
+        .collection("numbers")
+        .expr(ObjectExpr::index(IndexExpr::all("by_value")).with_select(SelectExpr::get()))
+        .expr(
+            ObjectExpr::index(IndexExpr::lookup("by_value", [f64::NAN]))
+                .with_select(SelectExpr::get()),
+        );
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert_eq!(result.take_result(0).unwrap().keys, [b"c", b"a", b"e"]);
+    assert!(result.take_result(1).unwrap().keys.is_empty());
+}
+
+#[tokio::test]
+async fn persist_without_expiration() {
+    let mut client = connect().await;
+    setup(&mut client, "app", &["a"]).await;
+    client
+        .batch(Batch::new().db("app").collection("a").set("x", 1).build())
+        .await
+        .unwrap();
+
+    let batch = Batch::new()
+        .db("app")
+        .collection("a")
+        .mutate("x", MutateExpr::persist())
+        .select("x", SelectExpr::version());
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let mut result = shape.decode(resp).unwrap();
+    assert!(!result.take::<bool>(0).unwrap());
+    assert_eq!(result.take::<i64>(1).unwrap(), 1);
+}
+
+#[tokio::test]
+async fn recovers_from_poisoned_lock() {
+    let server = MemoryServer::new();
+    let universe = server.universe.clone();
+    let poisoned = std::thread::spawn(move || {
+        let _guard = universe.lock().unwrap();
+        panic!("poisons the lock");
+    })
+    .join();
+    assert!(poisoned.is_err());
+
+    let mut client = connect_to(server).await;
+    let batch = Batch::new().db("nope").collection("a").get("x");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    let err = shape.decode(resp).unwrap().take_result(0).unwrap().error;
+    assert_eq!(err.unwrap().code(), ObjectErrorCode::NotFound);
+    setup(&mut client, "app", &["a"]).await;
+    let batch = Batch::new().db("app").collection("a").set("x", 1).get("x");
+    let shape = batch.shape();
+    let resp = client.batch(batch.build()).await.unwrap().into_inner();
+    assert_eq!(shape.decode(resp).unwrap().take::<i64>(1).unwrap(), 1);
+}
@@ -0,0 +1,861 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::Bound,
+    time::SystemTime,
+};
+
+use tonic::{Code, Status};
+
+use super::collection::{check_options, range_contains, Change, Changes, Collection};
+use crate::v1::{universe_request::Request, universe_response::Response, *};
+
+const MAX_PAGE_SIZE: u64 = 100;
+const MAX_WATCH_EVENTS: usize = 100;
+const MAX_RETAINED_CHANGES: usize = 10_000;
+
+#[derive(Default)]
+pub(super) struct Universe {
+    next_id: u64,
+    sequence: u64,
+    databases: BTreeMap<String, Database>,
+}
+
+struct Database {
+    desc: DatabaseDesc,
+    collections: BTreeMap<String, Collection>,
+    /// The latest changes committed to the database, in sequence order.
+    changes: VecDeque<Change>,
+    /// The sequence number up to which changes are no longer retained.
+    compacted_sequence: u64,
+    create_time: SystemTime,
+    update_time: SystemTime,
+}
+
+impl Database {
+    fn desc(&self) -> DatabaseDesc {
+        let mut properties = DatabaseProperties {
+            num_collections: self.collections.len() as u64,
+            create_time: Some(self.create_time.into()),
+            update_time: Some(self.update_time.into()),
+            ..Default::default()
+        };
+        for co in self.collections.values() {
+            let co_properties = co.desc().properties.unwrap_or_default();
+            properties.num_objects += co_properties.num_objects;
+            properties.logical_bytes += co_properties.logical_bytes;
+            properties.physical_bytes += co_properties.physical_bytes;
+        }
+        properties.last_write_time = self
+            .collections
+            .values()
+            .filter_map(|co| co.last_write_time)
+            .max()
+            .map(Into::into);
+        DatabaseDesc {
+            properties: Some(properties),
+            ..self.desc.clone()
+        }
+    }
+
+    fn collection(&self, name: &str) -> Result<&Collection, Status> {
+        self.collections
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("collection {} not found", name)))
+    }
+
+    fn collection_by_id(&self, id: u64) -> Result<&Collection, Status> {
+        self.collections
+            .values()
+            .find(|co| co.desc.id == id)
+            .ok_or_else(|| Status::not_found(format!("collection with id {} not found", id)))
+    }
+
+    fn collection_mut(&mut self, name: &str) -> Result<&mut Collection, Status> {
+        self.collections
+            .get_mut(name)
+            .ok_or_else(|| Status::not_found(format!("collection {} not found", name)))
+    }
+
+    fn compact_changes(&mut self) {
+        while self.changes.len() > MAX_RETAINED_CHANGES {
+            if let Some(change) = self.changes.pop_front() {
+                self.compacted_sequence = change.event.sequence;
+            }
+        }
+    }
+
+    /// Reads the collections as of the sequence number, by reverting the
+    /// later changes on copies of the collections.
+    fn read_snapshot(
+        &self,
+        req: DatabaseRequest,
+        sequence: u64,
+    ) -> Result<DatabaseResponse, Status> {
+        let start = self
+            .changes
+            .partition_point(|change| change.event.sequence <= sequence);
+        let mut responses = Vec::with_capacity(req.requests.len());
+        for req in req.requests {
+            let mut co = self.collection(&req.name)?.clone();
+            for change in self.changes.range(start..).rev() {
+                if change.collection_id == co.desc.id {
+                    co.undo(change);
+                }
+            }
+            let mut changes = Changes {
+                sequence,
+                changes: Vec::new(),
+            };
+            let results = req
+                .exprs
+                .iter()
+                .map(|expr| co.execute(expr, &mut changes))
+                .collect();
+            responses.push(CollectionResponse { results });
+        }
+        Ok(DatabaseResponse {
+            responses,
+            sequence,
+        })
+    }
+}
+
+impl Universe {
+    /// Handles universe requests before database requests, in order.
+    ///
+    /// A failed request aborts the batch, but the changes of the previous
+    /// requests are kept.
+    pub(super) fn batch(&mut self, req: BatchRequest) -> Result<BatchResponse, Status> {
+        let resp = if req.atomic {
+            self.atomic_batch(req)
+        } else {
+            self.batch_requests(req)
+        };
+        for db in self.databases.values_mut() {
+            db.compact_changes();
+        }
+        resp
+    }
+
+    fn batch_requests(&mut self, req: BatchRequest) -> Result<BatchResponse, Status> {
+        let universes = req
+            .universes
+            .into_iter()
+            .map(|req| self.universe(req))
+            .collect::<Result<_, _>>()?;
+        let databases = req
+            .databases
+            .into_iter()
+            .map(|req| {
+                let num_exprs: Vec<_> = req.requests.iter().map(|co| co.exprs.len()).collect();
+                self.database(req)
+                    .unwrap_or_else(|status| failed_database(&num_exprs, &status))
+            })
+            .collect();
+        Ok(BatchResponse {
+            databases,
+            universes,
+        })
+    }
+
+    /// Handles the database requests of an atomic batch.
+    ///
+    /// The collections touched by the batch are saved beforehand and restored
+    /// if any expression fails. Since batches are applied one at a time,
+    /// transactions never conflict, so they are never aborted.
+    fn atomic_batch(&mut self, mut req: BatchRequest) -> Result<BatchResponse, Status> {
+        if !req.universes.is_empty() {
+            return Err(Status::invalid_argument(
+                "universe requests can not be part of an atomic batch",
+            ));
+        }
+        for db_req in &mut req.databases {
+            self.resolve_names(db_req)?;
+        }
+        let mut saved = BTreeMap::new();
+        let mut num_changes = BTreeMap::new();
+        for db_req in &req.databases {
+            let db = self.db(&db_req.name)?;
+            num_changes.insert(db_req.name.clone(), db.changes.len());
+            for co_req in &db_req.requests {
+                let co = db.collection(&co_req.name)?;
+                saved
+                    .entry((db_req.name.clone(), co_req.name.clone()))
+                    .or_insert_with(|| co.clone());
+            }
+        }
+        let sequence = self.sequence;
+        match self.apply_atomic(req.databases) {
+            Ok(databases) => Ok(BatchResponse {
+                databases,
+                universes: Vec::new(),
+            }),
+            Err(status) => {
+                for ((dbname, name), co) in saved {
+                    self.db_mut(&dbname)?.collections.insert(name, co);
+                }
+                for (dbname, len) in num_changes {
+                    self.db_mut(&dbname)?.changes.truncate(len);
+                }
+                self.sequence = sequence;
+                Err(status)
+            }
+        }
+    }
+
+    /// Applies the database requests of an atomic batch, and stops at the
+    /// first failed request or expression, which the caller rolls back.
+    fn apply_atomic(
+        &mut self,
+        requests: Vec<DatabaseRequest>,
+    ) -> Result<Vec<DatabaseResponse>, Status> {
+        let mut databases = Vec::with_capacity(requests.len());
+        for db_req in requests {
+            let dbname = db_req.name.clone();
+            let names: Vec<_> = db_req.requests.iter().map(|co| co.name.clone()).collect();
+            let resp = self.database(db_req)?;
+            let failure = names
+                .into_iter()
+                .zip(&resp.responses)
+                .find_map(|(name, co)| {
+                    let err = co.results.iter().find_map(|r| r.error.as_ref())?;
+                    Some((name, err.clone()))
+                });
+            if let Some((name, err)) = failure {
+                let msg = format!(
+                    "transaction rolled back by collection {} in database {}: {}",
+                    name, dbname, err
+                );
+                return Err(Status::failed_precondition(msg));
+            }
+            databases.push(resp);
+        }
+        Ok(databases)
+    }
+
+    /// Returns the next chunk of a scan, with at most `limit` objects.
+    pub(super) fn scan(&self, req: &ScanRequest, limit: u64) -> Result<ScanResponse, Status> {
+        let co = self.scan_collection(req)?;
+        co.scan(req, limit)
+            .map_err(|err| Status::failed_precondition(err.to_string()))
+    }
+
+    /// Returns the collection of a scan, addressed by id or by names.
+    pub(super) fn scan_collection(&self, req: &ScanRequest) -> Result<&Collection, Status> {
+        match req.id {
+            0 => self.db(&req.dbname)?.collection(&req.name),
+            id => Ok(self.collection_by_id(id)?.1),
+        }
+    }
+
+    /// Checks a watch request and returns the sequence number to watch after.
+    ///
+    /// The database and the collection of the request are resolved to ids, so
+    /// that the watch follows them when they are renamed.
+    pub(super) fn start_watch(&self, req: &mut WatchRequest) -> Result<u64, Status> {
+        let db = match (req.id, req.dbid) {
+            (0, 0) => self.db(&req.dbname)?,
+            (0, dbid) => self.db_by_id(dbid)?,
+            (id, _) => self.collection_by_id(id)?.0,
+        };
+        if req.id == 0 && !req.name.is_empty() {
+            req.id = db.collection(&req.name)?.desc.id;
+        }
+        req.dbid = db.desc.id;
+        if let Some(range) = &req.range {
+            if req.id == 0 {
+                return Err(Status::invalid_argument(
+                    "watching a range requires a collection",
+                ));
+            }
+            range_contains(range, &[]).map_err(|err| Status::invalid_argument(err.message))?;
+        }
+        match req.resume_sequence {
+            0 => Ok(self.sequence),
+            after if after < db.compacted_sequence => Err(Status::out_of_range(format!(
+                "changes after sequence {} are no longer retained",
+                after
+            ))),
+            after => Ok(after),
+        }
+    }
+
+    /// Returns the watched changes after the sequence number `after`, and the
+    /// sequence number to watch after next time.
+    pub(super) fn watch(
+        &self,
+        req: &WatchRequest,
+        after: u64,
+    ) -> Result<(Vec<ChangeEvent>, u64), Status> {
+        // Changes are matched by collection id, so that the changes committed
+        // before a rename are watched too.
+        let (db, collection_id) = match req.id {
+            0 => (self.db_by_id(req.dbid)?, None),
+            id => (self.collection_by_id(id)?.0, Some(id)),
+        };
+        if after < db.compacted_sequence {
+            return Err(Status::out_of_range(format!(
+                "changes after sequence {} are no longer retained",
+                after
+            )));
+        }
+        let start = db
+            .changes
+            .partition_point(|change| change.event.sequence <= after);
+        let mut events = Vec::new();
+        let mut next = after;
+        for change in db.changes.range(start..) {
+            if events.len() == MAX_WATCH_EVENTS {
+                break;
+            }
+            let event = &change.event;
+            next = event.sequence;
+            if matches!(collection_id, Some(id) if change.collection_id != id) {
+                continue;
+            }
+            if let Some(range) = &req.range {
+                let contained = range_contains(range, &event.key)
+                    .map_err(|err| Status::invalid_argument(err.message))?;
+                if !contained {
+                    continue;
+                }
+            }
+            events.push(change.event(req.with_old_value));
+        }
+        Ok((events, next))
+    }
+
+    /// Returns the sequence number of the snapshot to read, or `None` to read
+    /// the latest changes.
+    ///
+    /// The latest changes are never stale, so bounded staleness reads are
+    /// served as strong reads.
+    fn read_sequence(&self, req: &DatabaseRequest) -> Result<Option<u64>, Status> {
+        let options = match &req.read_options {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+        let consistency = ReadConsistency::from_i32(options.consistency).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown read consistency {}", options.consistency))
+        })?;
+        let mutates = req
+            .requests
+            .iter()
+            .flat_map(|co| &co.exprs)
+            .any(|expr| expr.mutate.is_some());
+        if consistency != ReadConsistency::Strong && mutates {
+            return Err(Status::invalid_argument(format!(
+                "{:?} reads can not contain mutations",
+                consistency
+            )));
+        }
+        let sequence = match consistency {
+            ReadConsistency::Strong => return Ok(None),
+            ReadConsistency::BoundedStaleness => options.min_sequence,
+            ReadConsistency::Snapshot => options.snapshot_sequence,
+        };
+        if sequence > self.sequence {
+            return Err(Status::invalid_argument(format!(
+                "sequence {} has not been committed",
+                sequence
+            )));
+        }
+        if consistency == ReadConsistency::BoundedStaleness
+            || sequence == 0
+            || sequence == self.sequence
+        {
+            return Ok(None);
+        }
+        if sequence < self.db(&req.name)?.compacted_sequence {
+            return Err(Status::out_of_range(format!(
+                "snapshot at sequence {} is no longer retained",
+                sequence
+            )));
+        }
+        Ok(Some(sequence))
+    }
+
+    fn database(&mut self, mut req: DatabaseRequest) -> Result<DatabaseResponse, Status> {
+        self.resolve_names(&mut req)?;
+        if let Some(snapshot) = self.read_sequence(&req)? {
+            return self.db(&req.name)?.read_snapshot(req, snapshot);
+        }
+        let sequence = &mut self.sequence;
+        let db = self
+            .databases
+            .get_mut(&req.name)
+            .ok_or_else(|| Status::not_found(format!("database {} not found", req.name)))?;
+        // Checks the collections first, so that a failed request applies
+        // nothing.
+        if let Some(co_req) = req
+            .requests
+            .iter()
+            .find(|co_req| !db.collections.contains_key(&co_req.name))
+        {
+            return Err(Status::not_found(format!(
+                "collection {} not found",
+                co_req.name
+            )));
+        }
+        let mut responses = Vec::with_capacity(req.requests.len());
+        for req in req.requests {
+            let co = db
+                .collections
+                .get_mut(&req.name)
+                .ok_or_else(|| Status::not_found(format!("collection {} not found", req.name)))?;
+            let mut changes = Changes {
+                sequence: *sequence,
+                changes: Vec::new(),
+            };
+            let results = req
+                .exprs
+                .iter()
+                .map(|expr| co.execute(expr, &mut changes))
+                .collect();
+            *sequence = changes.sequence;
+            db.changes.extend(changes.changes);
+            responses.push(CollectionResponse { results });
+        }
+        Ok(DatabaseResponse {
+            responses,
+            sequence: *sequence,
+        })
+    }
+
+    /// Replaces the ids in a database request with the names they address.
+    fn resolve_names(&self, req: &mut DatabaseRequest) -> Result<(), Status> {
+        if req.id != 0 {
+            req.name = self.db_by_id(req.id)?.desc.name.clone();
+            req.id = 0;
+        }
+        if req.requests.iter().all(|co_req| co_req.id == 0) {
+            return Ok(());
+        }
+        let db = self.db(&req.name)?;
+        for co_req in &mut req.requests {
+            if co_req.id != 0 {
+                co_req.name = db.collection_by_id(co_req.id)?.desc.name.clone();
+                co_req.id = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn universe(&mut self, req: UniverseRequest) -> Result<UniverseResponse, Status> {
+        let req = req
+            .request
+            .ok_or_else(|| Status::invalid_argument("missing universe request"))?;
+        let res = match req {
+            Request::ListDatabases(req) => Response::ListDatabases(self.list_databases(req)),
+            Request::CreateDatabase(req) => Response::CreateDatabase(self.create_database(req)?),
+            Request::UpdateDatabase(req) => Response::UpdateDatabase(self.update_database(req)?),
+            Request::DeleteDatabase(req) => Response::DeleteDatabase(self.delete_database(req)?),
+            Request::DescribeDatabase(req) => {
+                Response::DescribeDatabase(self.describe_database(req)?)
+            }
+            Request::ListCollections(req) => Response::ListCollections(self.list_collections(req)?),
+            Request::CreateCollection(req) => {
+                Response::CreateCollection(self.create_collection(req)?)
+            }
+            Request::UpdateCollection(req) => {
+                Response::UpdateCollection(self.update_collection(req)?)
+            }
+            Request::DeleteCollection(req) => {
+                Response::DeleteCollection(self.delete_collection(req)?)
+            }
+            Request::DescribeCollection(req) => {
+                Response::DescribeCollection(self.describe_collection(req)?)
+            }
+            Request::CreateIndex(req) => Response::CreateIndex(self.create_index(req)?),
+            Request::DropIndex(req) => Response::DropIndex(self.drop_index(req)?),
+            Request::ListIndexes(req) => Response::ListIndexes(self.list_indexes(req)?),
+            Request::RenameDatabase(req) => Response::RenameDatabase(self.rename_database(req)?),
+            Request::RenameCollection(req) => {
+                Response::RenameCollection(self.rename_collection(req)?)
+            }
+        };
+        Ok(UniverseResponse {
+            response: Some(res),
+        })
+    }
+
+    fn db(&self, name: &str) -> Result<&Database, Status> {
+        self.databases
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("database {} not found", name)))
+    }
+
+    fn db_mut(&mut self, name: &str) -> Result<&mut Database, Status> {
+        self.databases
+            .get_mut(name)
+            .ok_or_else(|| Status::not_found(format!("database {} not found", name)))
+    }
+
+    fn db_by_id(&self, id: u64) -> Result<&Database, Status> {
+        self.databases
+            .values()
+            .find(|db| db.desc.id == id)
+            .ok_or_else(|| Status::not_found(format!("database with id {} not found", id)))
+    }
+
+    /// Returns the collection of the id and its database.
+    fn collection_by_id(&self, id: u64) -> Result<(&Database, &Collection), Status> {
+        self.databases
+            .values()
+            .find_map(|db| Some((db, db.collection_by_id(id).ok()?)))
+            .ok_or_else(|| Status::not_found(format!("collection with id {} not found", id)))
+    }
+
+    /// Returns the name of a database, addressed by id if the id is not zero.
+    fn db_name(&self, name: &str, id: u64) -> Result<String, Status> {
+        match id {
+            0 => Ok(name.to_owned()),
+            id => Ok(self.db_by_id(id)?.desc.name.clone()),
+        }
+    }
+
+    /// Returns the names of the database and the collection, addressed by id
+    /// if the id is not zero.
+    fn collection_names(
+        &self,
+        dbname: &str,
+        name: &str,
+        id: u64,
+    ) -> Result<(String, String), Status> {
+        match id {
+            0 => Ok((dbname.to_owned(), name.to_owned())),
+            id => {
+                let (db, co) = self.collection_by_id(id)?;
+                Ok((db.desc.name.clone(), co.desc.name.clone()))
+            }
+        }
+    }
+
+    fn list_databases(&self, req: ListDatabasesRequest) -> ListDatabasesResponse {
+        let (descs, next_page_token) = paginate(
+            &self.databases,
+            req.page_size,
+            &req.page_token,
+            Database::desc,
+        );
+        ListDatabasesResponse {
+            descs,
+            next_page_token,
+        }
+    }
+
+    fn create_database(
+        &mut self,
+        req: CreateDatabaseRequest,
+    ) -> Result<CreateDatabaseResponse, Status> {
+        check_name(&req.name)?;
+        if self.databases.contains_key(&req.name) {
+            return Err(Status::already_exists(format!(
+                "database {} already exists",
+                req.name
+            )));
+        }
+        self.next_id += 1;
+        let now = SystemTime::now();
+        let db = Database {
+            desc: DatabaseDesc {
+                id: self.next_id,
+                name: req.name.clone(),
+                options: req.options,
+                properties: None,
+            },
+            collections: BTreeMap::new(),
+            changes: VecDeque::new(),
+            compacted_sequence: self.sequence,
+            create_time: now,
+            update_time: now,
+        };
+        let desc = db.desc();
+        self.databases.insert(req.name, db);
+        Ok(CreateDatabaseResponse { desc: Some(desc) })
+    }
+
+    fn update_database(
+        &mut self,
+        req: UpdateDatabaseRequest,
+    ) -> Result<UpdateDatabaseResponse, Status> {
+        let name = self.db_name(&req.name, req.id)?;
+        let db = self.db_mut(&name)?;
+        db.desc.options = req.options;
+        db.update_time = SystemTime::now();
+        Ok(UpdateDatabaseResponse {
+            desc: Some(db.desc()),
+        })
+    }
+
+    fn delete_database(
+        &mut self,
+        req: DeleteDatabaseRequest,
+    ) -> Result<DeleteDatabaseResponse, Status> {
+        let name = self.db_name(&req.name, req.id)?;
+        self.databases
+            .remove(&name)
+            .ok_or_else(|| Status::not_found(format!("database {} not found", name)))?;
+        Ok(DeleteDatabaseResponse {})
+    }
+
+    fn describe_database(
+        &self,
+        req: DescribeDatabaseRequest,
+    ) -> Result<DescribeDatabaseResponse, Status> {
+        let db = self.db(&self.db_name(&req.name, req.id)?)?;
+        Ok(DescribeDatabaseResponse {
+            desc: Some(db.desc()),
+        })
+    }
+
+    fn list_collections(
+        &self,
+        req: ListCollectionsRequest,
+    ) -> Result<ListCollectionsResponse, Status> {
+        let db = self.db(&self.db_name(&req.name, req.id)?)?;
+        let (descs, next_page_token) = paginate(
+            &db.collections,
+            req.page_size,
+            &req.page_token,
+            Collection::desc,
+        );
+        Ok(ListCollectionsResponse {
+            descs,
+            next_page_token,
+        })
+    }
+
+    fn create_collection(
+        &mut self,
+        req: CreateCollectionRequest,
+    ) -> Result<CreateCollectionResponse, Status> {
+        check_name(&req.name)?;
+        check_options(&req.options)?;
+        let dbname = self.db_name(&req.dbname, req.dbid)?;
+        if self.db(&dbname)?.collections.contains_key(&req.name) {
+            return Err(Status::already_exists(format!(
+                "collection {} already exists",
+                req.name
+            )));
+        }
+        // Collection ids are unique within the universe, so that they stay
+        // unique when collections move between databases.
+        self.next_id += 1;
+        let id = self.next_id;
+        let db = self.db_mut(&dbname)?;
+        let co = Collection::new(CollectionDesc {
+            id,
+            name: req.name.clone(),
+            options: req.options,
+            properties: None,
+        });
+        let desc = co.desc();
+        db.collections.insert(req.name, co);
+        Ok(CreateCollectionResponse { desc: Some(desc) })
+    }
+
+    fn update_collection(
+        &mut self,
+        req: UpdateCollectionRequest,
+    ) -> Result<UpdateCollectionResponse, Status> {
+        check_options(&req.options)?;
+        let (dbname, name) = self.collection_names(&req.dbname, &req.name, req.id)?;
+        let co = self.db_mut(&dbname)?.collection_mut(&name)?;
+        co.update_options(req.options)?;
+        Ok(UpdateCollectionResponse {
+            desc: Some(co.desc()),
+        })
+    }
+
+    fn delete_collection(
+        &mut self,
+        req: DeleteCollectionRequest,
+    ) -> Result<DeleteCollectionResponse, Status> {
+        let (dbname, name) = self.collection_names(&req.dbname, &req.name, req.id)?;
+        self.db_mut(&dbname)?
+            .collections
+            .remove(&name)
+            .ok_or_else(|| Status::not_found(format!("collection {} not found", name)))?;
+        Ok(DeleteCollectionResponse {})
+    }
+
+    fn describe_collection(
+        &self,
+        req: DescribeCollectionRequest,
+    ) -> Result<DescribeCollectionResponse, Status> {
+        let (dbname, name) = self.collection_names(&req.dbname, &req.name, req.id)?;
+        let co = self.db(&dbname)?.collection(&name)?;
+        Ok(DescribeCollectionResponse {
+            desc: Some(co.describe(req.exact)),
+        })
+    }
+
+    fn rename_database(
+        &mut self,
+        req: RenameDatabaseRequest,
+    ) -> Result<RenameDatabaseResponse, Status> {
+        let name = self.db_name(&req.name, req.id)?;
+        check_name(&req.new_name)?;
+        self.db(&name)?;
+        if name != req.new_name {
+            if self.databases.contains_key(&req.new_name) {
+                return Err(Status::already_exists(format!(
+                    "database {} already exists",
+                    req.new_name
+                )));
+            }
+            if let Some(mut db) = self.databases.remove(&name) {
+                db.desc.name = req.new_name.clone();
+                self.databases.insert(req.new_name.clone(), db);
+            }
+        }
+        Ok(RenameDatabaseResponse {
+            desc: Some(self.db(&req.new_name)?.desc()),
+        })
+    }
+
+    /// Renames a collection or moves it to another database, together with
+    /// its retained changes.
+    fn rename_collection(
+        &mut self,
+        req: RenameCollectionRequest,
+    ) -> Result<RenameCollectionResponse, Status> {
+        let (dbname, name) = self.collection_names(&req.dbname, &req.name, req.id)?;
+        self.db(&dbname)?.collection(&name)?;
+        let new_dbname = match req.new_dbname.as_str() {
+            "" => dbname.clone(),
+            new_dbname => new_dbname.to_owned(),
+        };
+        let new_name = match req.new_name.as_str() {
+            "" => name.clone(),
+            new_name => new_name.to_owned(),
+        };
+        if (&new_dbname, &new_name) != (&dbname, &name)
+            && self.db(&new_dbname)?.collections.contains_key(&new_name)
+        {
+            return Err(Status::already_exists(format!(
+                "collection {} already exists",
+                new_name
+            )));
+        }
+        let db = self.db_mut(&dbname)?;
+        let mut co = db.collections.remove(&name).expect("collection exists");
+        co.desc.name = new_name.clone();
+        if new_dbname != dbname {
+            let (moved, kept) = db
+                .changes
+                .drain(..)
+                .partition::<Vec<_>, _>(|change| change.collection_id == co.desc.id);
+            db.changes = kept.into();
+            let compacted_sequence = db.compacted_sequence;
+            let new_db = self.db_mut(&new_dbname)?;
+            let mut changes: Vec<_> = new_db.changes.drain(..).chain(moved).collect();
+            changes.sort_by_key(|change| change.event.sequence);
+            new_db.changes = changes.into();
+            new_db.compacted_sequence = new_db.compacted_sequence.max(compacted_sequence);
+        }
+        let desc = co.desc();
+        self.db_mut(&new_dbname)?.collections.insert(new_name, co);
+        Ok(RenameCollectionResponse { desc: Some(desc) })
+    }
+
+    fn create_index(&mut self, req: CreateIndexRequest) -> Result<CreateIndexResponse, Status> {
+        check_name(&req.name)?;
+        let (dbname, name) = self.collection_names(&req.dbname, &req.collection, req.coid)?;
+        let co = self.db_mut(&dbname)?.collection_mut(&name)?;
+        let desc = co.create_index(&req.name, req.options.unwrap_or_default())?;
+        Ok(CreateIndexResponse { desc: Some(desc) })
+    }
+
+    fn drop_index(&mut self, req: DropIndexRequest) -> Result<DropIndexResponse, Status> {
+        let (dbname, name) = self.collection_names(&req.dbname, &req.collection, req.coid)?;
+        let co = self.db_mut(&dbname)?.collection_mut(&name)?;
+        co.drop_index(&req.name)?;
+        Ok(DropIndexResponse {})
+    }
+
+    fn list_indexes(&self, req: ListIndexesRequest) -> Result<ListIndexesResponse, Status> {
+        let (dbname, name) = self.collection_names(&req.dbname, &req.collection, req.coid)?;
+        let co = self.db(&dbname)?.collection(&name)?;
+        Ok(ListIndexesResponse {
+            descs: co.index_descs(),
+        })
+    }
+}
+
+/// Returns the response of a database request that failed as a whole, where
+/// each expression fails with the error of the request.
+fn failed_database(num_exprs: &[usize], status: &Status) -> DatabaseResponse {
+    let code = match status.code() {
+        Code::NotFound => ObjectErrorCode::NotFound,
+        Code::AlreadyExists => ObjectErrorCode::AlreadyExists,
+        Code::OutOfRange => ObjectErrorCode::OutOfRange,
+        Code::InvalidArgument => ObjectErrorCode::InvalidArgument,
+        _ => ObjectErrorCode::Unknown,
+    };
+    let responses = num_exprs
+        .iter()
+        .map(|&n| CollectionResponse {
+            results: (0..n)
+                .map(|_| ObjectResult {
+                    error: Some(eval::error(code, status.message())),
+                    ..Default::default()
+                })
+                .collect(),
+        })
+        .collect();
+    DatabaseResponse {
+        responses,
+        sequence: 0,
+    }
+}
+
+fn check_name(name: &str) -> Result<(), Status> {
+    if name.is_empty() {
+        Err(Status::invalid_argument("name must not be empty"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns a page of descriptors after `page_token`, which is the name of the
+/// last entry of the previous page.
+fn paginate<T, D>(
+    map: &BTreeMap<String, T>,
+    page_size: u64,
+    page_token: &str,
+    desc: impl Fn(&T) -> D,
+) -> (Vec<D>, String) {
+    let page_size = match page_size {
+        0 => MAX_PAGE_SIZE,
+        n => n.min(MAX_PAGE_SIZE),
+    } as usize;
+    let start = if page_token.is_empty() {
+        Bound::Unbounded
+    } else {
+        Bound::Excluded(page_token)
+    };
+    let mut iter = map.range::<str, _>((start, Bound::Unbounded));
+    let page: Vec<_> = iter.by_ref().take(page_size).collect();
+    let next_page_token = match (iter.next(), page.last()) {
+        (Some(_), Some((name, _))) => name.to_string(),
+        _ => String::new(),
+    };
+    let descs = page.into_iter().map(|(_, v)| desc(v)).collect();
+    (descs, next_page_token)
+}
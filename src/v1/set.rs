@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{BTreeSet, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    hash::Hash,
+};
 
 use crate::v1::*;
 
@@ -25,86 +28,231 @@ impl<T: Into<ListValue>> From<T> for SetValue {
 }
 
 macro_rules! impl_set {
-    ($set_type:ty, $value_type:ty) => {
-        impl From<$set_type> for SetValue {
+    ($set_type:ty, $($bound:tt)*) => {
+        impl<$($bound)*> From<$set_type> for SetValue {
             fn from(set: $set_type) -> Self {
-                let keys: Vec<$value_type> = set.into_iter().collect();
+                let keys: Vec<T> = set.into_iter().collect();
                 keys.into()
             }
         }
 
-        impl From<$set_type> for value::Value {
+        impl<$($bound)*> From<$set_type> for value::Value {
             fn from(set: $set_type) -> Self {
                 SetValue::from(set).into()
             }
         }
 
-        impl TryFrom<SetValue> for $set_type {
-            type Error = SetValue;
+        impl<$($bound)*> TryFrom<SetValue> for $set_type {
+            type Error = ConversionError;
 
             fn try_from(v: SetValue) -> Result<Self, Self::Error> {
                 if let Some(keys) = v.keys {
-                    keys.try_into().map_err(|v| SetValue::from(v))
+                    keys.try_into().map_err(|err: ConversionError| {
+                        err.at("keys").map_value(|keys| {
+                            SetValue {
+                                keys: keys.try_into().ok(),
+                            }
+                            .into()
+                        })
+                    })
                 } else {
-                    Err(v)
+                    Err(ConversionError::new("list", Value::default())
+                        .at("keys")
+                        .with_value(v))
                 }
             }
         }
 
-        impl TryFrom<ListValue> for $set_type {
-            type Error = ListValue;
+        impl<$($bound)*> TryFrom<ListValue> for $set_type {
+            type Error = ConversionError;
 
             fn try_from(v: ListValue) -> Result<Self, Self::Error> {
-                let list: Vec<$value_type> = v.try_into()?;
+                let list: Vec<T> = v.try_into()?;
                 Ok(Self::from_iter(list))
             }
         }
 
-        impl TryFrom<value::Value> for $set_type {
-            type Error = value::Value;
+        impl<$($bound)*> TryFrom<value::Value> for $set_type {
+            type Error = ConversionError;
 
             fn try_from(v: value::Value) -> Result<Self, Self::Error> {
                 if let value::Value::SetValue(v) = v {
-                    v.try_into().map_err(|v| value::Value::from(v))
+                    v.try_into()
                 } else {
-                    Err(v)
+                    Err(ConversionError::new("set", v))
                 }
             }
         }
 
-        impl TryFrom<Value> for $set_type {
-            type Error = Value;
+        impl<$($bound)*> TryFrom<Value> for $set_type {
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
                 if let Some(v) = v.value {
-                    v.try_into().map_err(|v| Value::from(v))
+                    v.try_into()
                 } else {
-                    Err(v)
+                    Err(ConversionError::new("set", v))
                 }
             }
         }
 
-        impl TryFrom<Value> for Option<$set_type> {
-            type Error = Value;
+        impl<$($bound)*> TryFrom<Value> for Option<$set_type> {
+            type Error = ConversionError;
 
             fn try_from(v: Value) -> Result<Self, Self::Error> {
-                if let Some(v) = v.value {
-                    v.try_into().map(Some).map_err(|v| Value::from(v))
-                } else {
-                    Ok(None)
+                match v.value {
+                    None | Some(value::Value::NullValue(_)) => Ok(None),
+                    Some(v) => v.try_into().map(Some),
                 }
             }
         }
     };
 }
 
-macro_rules! impl_type {
-    ($value_type:ty) => {
-        impl_set!(HashSet<$value_type>, $value_type);
-        impl_set!(BTreeSet<$value_type>, $value_type);
+impl_set!(HashSet<T>, T: ListElement + Eq + Hash);
+impl_set!(BTreeSet<T>, T: ListElement + Ord);
+
+impl SortedSetValue {
+    fn try_into_members<K: ListElement>(self) -> Result<Vec<(K, f64)>, ConversionError> {
+        let scores = self.scores;
+        let members: Vec<K> = match self.members {
+            Some(members) => members.try_into().map_err(|err: ConversionError| {
+                err.at("members").map_value(|members| {
+                    SortedSetValue {
+                        members: members.try_into().ok(),
+                        scores: scores.clone(),
+                    }
+                    .into()
+                })
+            })?,
+            None => Vec::new(),
+        };
+        if members.len() == scores.len() {
+            Ok(members.into_iter().zip(scores).collect())
+        } else {
+            Err(ConversionError::new(
+                "sorted set with the same number of members and scores",
+                SortedSetValue {
+                    members: Some(members.into()),
+                    scores,
+                },
+            ))
+        }
+    }
+}
+
+impl<K: ListElement> From<Vec<(K, f64)>> for SortedSetValue {
+    fn from(members: Vec<(K, f64)>) -> Self {
+        let (members, scores): (Vec<K>, Vec<f64>) = members.into_iter().unzip();
+        Self {
+            members: Some(members.into()),
+            scores,
+        }
+    }
+}
+
+/// A sorted set of members and their scores.
+///
+/// A `BTreeMap<K, f64>` converts to a map value, so sorted set values convert
+/// to and from this wrapper or a `Vec<(K, f64)>` instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SortedSet<K>(pub BTreeMap<K, f64>);
+
+impl<K> From<BTreeMap<K, f64>> for SortedSet<K> {
+    fn from(members: BTreeMap<K, f64>) -> Self {
+        Self(members)
+    }
+}
+
+impl<K> From<SortedSet<K>> for BTreeMap<K, f64> {
+    fn from(set: SortedSet<K>) -> Self {
+        set.0
+    }
+}
+
+impl<K: Ord> FromIterator<(K, f64)> for SortedSet<K> {
+    fn from_iter<I: IntoIterator<Item = (K, f64)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<K: ListElement> From<SortedSet<K>> for SortedSetValue {
+    fn from(set: SortedSet<K>) -> Self {
+        set.0.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+macro_rules! impl_sorted_set {
+    ($set_type:ty, $($bound:tt)*) => {
+        impl<$($bound)*> From<$set_type> for value::Value {
+            fn from(set: $set_type) -> Self {
+                SortedSetValue::from(set).into()
+            }
+        }
+
+        impl<$($bound)*> TryFrom<SortedSetValue> for $set_type {
+            type Error = ConversionError;
+
+            fn try_from(v: SortedSetValue) -> Result<Self, Self::Error> {
+                Ok(v.try_into_members()?.into_iter().collect())
+            }
+        }
+
+        impl<$($bound)*> TryFrom<value::Value> for $set_type {
+            type Error = ConversionError;
+
+            fn try_from(v: value::Value) -> Result<Self, Self::Error> {
+                if let value::Value::SortedSetValue(v) = v {
+                    v.try_into()
+                } else {
+                    Err(ConversionError::new("sorted_set", v))
+                }
+            }
+        }
+
+        impl<$($bound)*> TryFrom<Value> for $set_type {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<Self, Self::Error> {
+                if let Some(v) = v.value {
+                    v.try_into()
+                } else {
+                    Err(ConversionError::new("sorted_set", v))
+                }
+            }
+        }
+
+        impl<$($bound)*> TryFrom<Value> for Option<$set_type> {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<Self, Self::Error> {
+                match v.value {
+                    None | Some(value::Value::NullValue(_)) => Ok(None),
+                    Some(v) => v.try_into().map(Some),
+                }
+            }
+        }
     };
 }
 
-impl_type!(i64);
-impl_type!(Vec<u8>);
-impl_type!(String);
+impl_sorted_set!(Vec<(K, f64)>, K: ListElement);
+impl_sorted_set!(SortedSet<K>, K: ListElement + Ord);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_set_round_trip() {
+        let members = BTreeMap::from([("a".to_owned(), 1.0), ("b".to_owned(), 0.5)]);
+        let set = SortedSet::from(members.clone());
+        let v = Value::from(set.clone());
+        assert!(matches!(v.value, Some(value::Value::SortedSetValue(_))));
+        assert_eq!(SortedSet::<String>::try_from(v.clone()).unwrap(), set);
+        assert_eq!(Vec::<(String, f64)>::try_from(v).unwrap().len(), 2);
+
+        let map = Value::from(members);
+        assert!(matches!(map.value, Some(value::Value::MapValue(_))));
+        assert!(SortedSet::<String>::try_from(map).is_err());
+    }
+}
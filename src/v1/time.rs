@@ -0,0 +1,202 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{self, SystemTime, UNIX_EPOCH};
+
+use crate::v1::*;
+
+const NANOS_PER_SECOND: i32 = 1_000_000_000;
+
+impl From<SystemTime> for Timestamp {
+    fn from(t: SystemTime) -> Self {
+        match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => Self {
+                seconds: d.as_secs().try_into().unwrap_or(i64::MAX),
+                nanos: d.subsec_nanos() as i32,
+            },
+            Err(err) => {
+                let d = err.duration();
+                let seconds = -d.as_secs().try_into().unwrap_or(i64::MAX);
+                match d.subsec_nanos() as i32 {
+                    0 => Self { seconds, nanos: 0 },
+                    nanos => Self {
+                        seconds: seconds - 1,
+                        nanos: NANOS_PER_SECOND - nanos,
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = ConversionError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        let time = if (0..NANOS_PER_SECOND).contains(&t.nanos) {
+            let nanos = time::Duration::from_nanos(t.nanos as u64);
+            if t.seconds >= 0 {
+                UNIX_EPOCH
+                    .checked_add(time::Duration::from_secs(t.seconds as u64))
+                    .and_then(|t| t.checked_add(nanos))
+            } else {
+                UNIX_EPOCH
+                    .checked_sub(time::Duration::from_secs(t.seconds.unsigned_abs()))
+                    .and_then(|t| t.checked_add(nanos))
+            }
+        } else {
+            None
+        };
+        time.ok_or_else(|| ConversionError::new("timestamp within the range of SystemTime", t))
+    }
+}
+
+impl From<time::Duration> for Duration {
+    fn from(d: time::Duration) -> Self {
+        Self {
+            seconds: d.as_secs().try_into().unwrap_or(i64::MAX),
+            nanos: d.subsec_nanos() as i32,
+        }
+    }
+}
+
+impl TryFrom<Duration> for time::Duration {
+    type Error = ConversionError;
+
+    fn try_from(d: Duration) -> Result<Self, Self::Error> {
+        if d.seconds >= 0 && (0..NANOS_PER_SECOND).contains(&d.nanos) {
+            Ok(Self::new(d.seconds as u64, d.nanos as u32))
+        } else {
+            Err(ConversionError::new("non-negative duration", d))
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    /// Leap seconds, whose nanos are 1e9 or more in chrono, are folded into the
+    /// next second.
+    fn from(t: chrono::DateTime<chrono::Utc>) -> Self {
+        let nanos = t.timestamp_subsec_nanos() as i32;
+        Self {
+            seconds: t.timestamp() + (nanos / NANOS_PER_SECOND) as i64,
+            nanos: nanos % NANOS_PER_SECOND,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Timestamp> for chrono::DateTime<chrono::Utc> {
+    type Error = ConversionError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        u32::try_from(t.nanos)
+            .ok()
+            .and_then(|nanos| Self::from_timestamp(t.seconds, nanos))
+            .ok_or_else(|| ConversionError::new("timestamp within the range of DateTime", t))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::Duration> for Duration {
+    fn from(d: chrono::Duration) -> Self {
+        Self {
+            seconds: d.num_seconds(),
+            nanos: d.subsec_nanos(),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Duration> for chrono::Duration {
+    type Error = ConversionError;
+
+    fn try_from(d: Duration) -> Result<Self, Self::Error> {
+        Self::try_seconds(d.seconds)
+            .and_then(|s| s.checked_add(&Self::nanoseconds(d.nanos as i64)))
+            .ok_or_else(|| ConversionError::new("duration within the range of chrono", d))
+    }
+}
+
+macro_rules! impl_type {
+    ($rust_type:ty, $value_type:ty) => {
+        impl From<$rust_type> for value::Value {
+            fn from(v: $rust_type) -> Self {
+                <$value_type>::from(v).into()
+            }
+        }
+
+        impl TryFrom<value::Value> for $rust_type {
+            type Error = ConversionError;
+
+            fn try_from(v: value::Value) -> Result<Self, Self::Error> {
+                <$value_type>::try_from(v)?.try_into()
+            }
+        }
+
+        impl TryFrom<Value> for $rust_type {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<Self, Self::Error> {
+                <$value_type>::try_from(v)?.try_into()
+            }
+        }
+
+        impl TryFrom<Value> for Option<$rust_type> {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<Self, Self::Error> {
+                Option::<$value_type>::try_from(v)?
+                    .map(TryInto::try_into)
+                    .transpose()
+            }
+        }
+
+        impl ListElement for $rust_type {
+            fn try_from_value(v: Value) -> Result<Self, ConversionError> {
+                v.try_into()
+            }
+        }
+    };
+}
+
+impl_type!(SystemTime, Timestamp);
+impl_type!(time::Duration, Duration);
+#[cfg(feature = "chrono")]
+impl_type!(chrono::DateTime<chrono::Utc>, Timestamp);
+#[cfg(feature = "chrono")]
+impl_type!(chrono::Duration, Duration);
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, hms: (u32, u32, u32), nanos: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .and_then(|d| d.and_hms_nano_opt(hms.0, hms.1, hms.2, nanos))
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn chrono_leap_second() {
+        let leap = utc(2016, 12, 31, (23, 59, 59), 1_500_000_000);
+        let next = utc(2017, 1, 1, (0, 0, 0), 500_000_000);
+        let t = Timestamp::from(leap);
+        assert_eq!(t, Timestamp::from(next));
+        assert_eq!(DateTime::<Utc>::try_from(t), Ok(next));
+    }
+}
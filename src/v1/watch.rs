@@ -0,0 +1,47 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::v1::*;
+
+impl WatchRequest {
+    /// Creates a request to watch all collections in a database.
+    pub fn new(dbname: impl Into<String>) -> Self {
+        Self {
+            dbname: dbname.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_collection(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Watches a range of keys in the collection.
+    pub fn with_range(mut self, range: impl Into<RangeExpr>) -> Self {
+        self.range = Some(range.into());
+        self
+    }
+
+    /// Resumes the watch after the change with the sequence number.
+    pub fn with_resume_sequence(mut self, sequence: u64) -> Self {
+        self.resume_sequence = sequence;
+        self
+    }
+
+    pub fn with_old_value(mut self, with_old_value: bool) -> Self {
+        self.with_old_value = with_old_value;
+        self
+    }
+}